@@ -0,0 +1,544 @@
+//! Bevy plugin loading a registry through the asset server
+//!
+//! Item files (`*.registry.json` by default, see
+//! [RegistryPlugin::with_extensions]) are loaded as [RegistrySource] assets,
+//! either from a mod folder or from a single bundle file holding an array of
+//! items. Once all sources are loaded, the registry is built on the
+//! [AsyncComputeTaskPool] and inserted as a [LoadedRegistry] resource:
+//!
+//! ```ignore
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(RegistryPlugin::<PartialGameRegistry>::folder("mods/base"))
+//!     .add_systems(Update, on_reload);
+//!
+//! fn on_reload(mut events: EventReader<RegistryReloaded<PartialGameRegistry>>) {
+//!     for event in events.read() {
+//!         info!("Changed items: {:?}", event.changed);
+//!     }
+//! }
+//! ```
+//!
+//! With asset hot reloading enabled in bevy, every change of the source
+//! files rebuilds the registry. Ids of the existing items are kept by
+//! reserving them in the new registry (see [PartialRegistry::reserve_ids]),
+//! unless some item was removed, in which case the registry is rebuilt from
+//! scratch. Deserialization errors are reported to bevy logs, and the
+//! previous registry is kept in place
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use bevy_app::{App, Plugin, Startup, Update};
+use bevy_asset::io::Reader;
+use bevy_asset::{
+    Asset, AssetApp, AssetEvent, AssetLoader, AssetServer, Assets, AsyncReadExt, BoxedFuture,
+    Handle, LoadContext, LoadedFolder, UntypedAssetId, VisitAssetDependencies,
+};
+use bevy_ecs::event::{Event, EventReader, EventWriter};
+use bevy_ecs::schedule::IntoSystemConfigs;
+use bevy_ecs::system::{Commands, Res, ResMut, Resource};
+use bevy_reflect::TypePath;
+use bevy_tasks::{block_on, AsyncComputeTaskPool, Task};
+use thiserror::Error;
+
+use crate::registry::path_identifier::PathIdentifier;
+use crate::registry::{LoadableRegistry, PartialRegistry, SerializationRegistry};
+use crate::serialization::error::DeserializationError;
+use crate::ItemId;
+
+/// Registry types usable with [RegistryPlugin]
+///
+/// Implemented for all loadable registries with thread-safe items and
+/// errors
+pub trait PluginRegistry:
+    LoadableRegistry<
+        Item: Clone + Send + Sync,
+        ItemKind: Send + Sync,
+        AssetKind: Send,
+        Error: Send,
+        Registry: Send + Sync + 'static,
+    > + Send
+    + 'static
+{
+}
+
+impl<R> PluginRegistry for R where
+    R: LoadableRegistry<
+            Item: Clone + Send + Sync,
+            ItemKind: Send + Sync,
+            AssetKind: Send,
+            Error: Send,
+            Registry: Send + Sync + 'static,
+        > + Send
+        + 'static
+{
+}
+
+/// Hook preparing every new partial registry before its items are
+/// deserialized, mainly used for inserting assets
+pub type PrepareRegistry<R> = fn(&mut R, &AssetServer) -> Result<(), DeserializationError<R>>;
+
+/// Location of the registry items in the asset sources
+#[derive(Debug, Clone)]
+pub enum RegistryLocation {
+    /// Folder with item files, loaded recursively
+    Folder(String),
+    /// Single file holding an array of items
+    Bundle(String),
+}
+
+/// Plugin loading the registry `R` through the asset server, see
+/// [module documentation](self)
+pub struct RegistryPlugin<R: PartialRegistry> {
+    location: RegistryLocation,
+    extensions: Vec<&'static str>,
+    prepare: Option<PrepareRegistry<R>>,
+}
+
+impl<R: PartialRegistry> RegistryPlugin<R> {
+    pub fn new(location: RegistryLocation) -> Self {
+        Self {
+            location,
+            extensions: vec!["registry.json"],
+            prepare: None,
+        }
+    }
+
+    /// Loads items from files in the folder
+    pub fn folder(path: impl Into<String>) -> Self {
+        Self::new(RegistryLocation::Folder(path.into()))
+    }
+
+    /// Loads items from a single bundle file
+    pub fn bundle(path: impl Into<String>) -> Self {
+        Self::new(RegistryLocation::Bundle(path.into()))
+    }
+
+    /// Sets file extensions handled by the [RegistrySource] loader,
+    /// `registry.json` by default
+    ///
+    /// Folders are scanned for these extensions only, so plain `json` should
+    /// be used with care, since it takes over json files of other loaders
+    pub fn with_extensions(mut self, extensions: impl IntoIterator<Item = &'static str>) -> Self {
+        self.extensions = extensions.into_iter().collect();
+        self
+    }
+
+    /// Sets the hook running on every new partial registry, see
+    /// [PrepareRegistry]
+    pub fn with_prepare(mut self, prepare: PrepareRegistry<R>) -> Self {
+        self.prepare = Some(prepare);
+        self
+    }
+}
+
+impl<R: PluginRegistry> Plugin for RegistryPlugin<R> {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<RegistrySource<R>>()
+            .register_asset_loader(RegistrySourceLoader::<R> {
+                extensions: self.extensions.clone(),
+                _registry: PhantomData,
+            })
+            .add_event::<RegistryReloaded<R>>()
+            .insert_resource(RegistryLoader::<R> {
+                location: self.location.clone(),
+                prepare: self.prepare,
+                folder: None,
+                sources: vec![],
+                snapshot: Default::default(),
+                dirty: false,
+                task: None,
+            })
+            .add_systems(Startup, load_sources::<R>)
+            .add_systems(
+                Update,
+                (track_sources::<R>, start_build::<R>, finish_build::<R>).chain(),
+            );
+    }
+}
+
+/// Complete registry built by [RegistryPlugin]
+///
+/// The registry is shared with the build task during hot reloading, so the
+/// ids of its items can be reserved off the main thread
+#[derive(Debug, Resource)]
+pub struct LoadedRegistry<T: Send + Sync + 'static>(pub Arc<T>);
+
+impl<T: Send + Sync + 'static> Deref for LoadedRegistry<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Event sent after the [LoadedRegistry] is replaced by the rebuilt one
+#[derive(Debug, Event)]
+pub struct RegistryReloaded<R: PluginRegistry> {
+    /// Items that were added, removed, or changed, ordered by their kind
+    /// and key. Collection items come with their keys, and singletons
+    /// without
+    pub changed: Vec<(R::ItemKind, Option<ItemId>)>,
+    /// Whether the registry was rebuilt from scratch, invalidating all ids
+    /// held outside of the registry
+    pub ids_changed: bool,
+}
+
+/// Serialized registry items loaded from a single file
+pub struct RegistrySource<R: LoadableRegistry> {
+    pub items: Vec<(PathIdentifier, R::Item)>,
+}
+
+impl<R: LoadableRegistry + 'static> TypePath for RegistrySource<R> {
+    fn type_path() -> &'static str {
+        type_name::<Self>()
+    }
+
+    fn short_type_path() -> &'static str {
+        "RegistrySource"
+    }
+}
+
+impl<R: LoadableRegistry + 'static> VisitAssetDependencies for RegistrySource<R> {
+    fn visit_dependencies(&self, _visit: &mut impl FnMut(UntypedAssetId)) {}
+}
+
+impl<R: LoadableRegistry<Item: Send + Sync> + 'static> Asset for RegistrySource<R> {}
+
+/// Error of reading a [RegistrySource]
+#[derive(Debug, Error)]
+pub enum RegistrySourceError {
+    #[error("Failed to read the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse the file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Loader of [RegistrySource]s, reading files that hold either a single
+/// item, or an array of items
+struct RegistrySourceLoader<R> {
+    extensions: Vec<&'static str>,
+    _registry: PhantomData<fn() -> R>,
+}
+
+impl<R: LoadableRegistry<Item: Send + Sync> + 'static> AssetLoader for RegistrySourceLoader<R> {
+    type Asset = RegistrySource<R>;
+    type Settings = ();
+    type Error = RegistrySourceError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut data = vec![];
+            reader.read_to_end(&mut data).await?;
+            let path = PathIdentifier::from(load_context.path());
+            let is_bundle = data.iter().find(|c| !c.is_ascii_whitespace()) == Some(&b'[');
+            let items = if is_bundle {
+                let items: Vec<R::Item> = serde_json::from_slice(&data)?;
+                // Bundle items are told apart by their position in the file
+                let file = path.to_string();
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let index = i.to_string();
+                        let path = PathIdentifier::from_components(
+                            file.split('/').chain([index.as_str()]),
+                        );
+                        (path, item)
+                    })
+                    .collect()
+            } else {
+                vec![(path, serde_json::from_slice(&data)?)]
+            };
+            Ok(RegistrySource { items })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+/// Debug output of the serialized items of the last built registry, keyed
+/// by the item kind name and the item key
+type ItemSnapshot<R> =
+    BTreeMap<(String, Option<ItemId>), (<R as SerializationRegistry>::ItemKind, String)>;
+
+/// Result of building the registry on the task pool
+struct BuildResult<R: PluginRegistry> {
+    result: Result<R::Registry, DeserializationError<R>>,
+    /// Whether the ids of the previous registry were kept
+    hot: bool,
+    /// Items that differ from the previous build
+    changed: Vec<(R::ItemKind, Option<ItemId>)>,
+    snapshot: ItemSnapshot<R>,
+}
+
+/// State of the [RegistryPlugin]
+#[derive(Resource)]
+struct RegistryLoader<R: PluginRegistry> {
+    location: RegistryLocation,
+    prepare: Option<PrepareRegistry<R>>,
+    folder: Option<Handle<LoadedFolder>>,
+    sources: Vec<Handle<RegistrySource<R>>>,
+    snapshot: Arc<ItemSnapshot<R>>,
+    /// Whether sources changed since the last build
+    dirty: bool,
+    task: Option<Task<BuildResult<R>>>,
+}
+
+fn load_sources<R: PluginRegistry>(
+    mut loader: ResMut<RegistryLoader<R>>,
+    server: Res<AssetServer>,
+) {
+    match loader.location.clone() {
+        RegistryLocation::Folder(path) => loader.folder = Some(server.load_folder(path)),
+        RegistryLocation::Bundle(path) => loader.sources = vec![server.load(path)],
+    }
+}
+
+/// Marks the loader dirty when any of the sources changes, and forgets the
+/// removed ones
+fn track_sources<R: PluginRegistry>(
+    mut loader: ResMut<RegistryLoader<R>>,
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    mut source_events: EventReader<AssetEvent<RegistrySource<R>>>,
+    folders: Res<Assets<LoadedFolder>>,
+) {
+    let loader = &mut *loader;
+    for event in folder_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if loader.folder.as_ref().map(Handle::id) != Some(*id) {
+            continue;
+        }
+        let Some(folder) = folders.get(*id) else {
+            continue;
+        };
+        loader.sources = folder
+            .handles
+            .iter()
+            .filter_map(|handle| handle.clone().try_typed().ok())
+            .collect();
+        loader.dirty = true;
+    }
+
+    for event in source_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if loader.sources.iter().any(|source| source.id() == *id) =>
+            {
+                loader.dirty = true;
+            }
+            // Removed sources never load again, waiting for them would stall
+            // the build
+            AssetEvent::Removed { id } => {
+                let count = loader.sources.len();
+                loader.sources.retain(|source| source.id() != *id);
+                loader.dirty |= loader.sources.len() != count;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Starts building the registry once all sources are loaded
+fn start_build<R: PluginRegistry>(
+    mut loader: ResMut<RegistryLoader<R>>,
+    sources: Res<Assets<RegistrySource<R>>>,
+    server: Res<AssetServer>,
+    registry: Option<Res<LoadedRegistry<R::Registry>>>,
+) {
+    if !loader.dirty || loader.task.is_some() {
+        return;
+    }
+    let Some(mut loaded) = loader
+        .sources
+        .iter()
+        .map(|handle| sources.get(handle))
+        .collect::<Option<Vec<_>>>()
+    else {
+        // Some sources are still loading
+        return;
+    };
+    loaded.sort_by_cached_key(|source| source.items.first().map(|(path, _)| path.to_string()));
+    let items: Vec<_> = loaded
+        .into_iter()
+        .flat_map(|source| source.items.iter().cloned())
+        .collect();
+    loader.dirty = false;
+
+    let prepare = loader.prepare;
+    let server = server.clone();
+    let previous = registry.map(|registry| Arc::clone(&registry.0));
+    let snapshot = Arc::clone(&loader.snapshot);
+    loader.task = Some(
+        AsyncComputeTaskPool::get()
+            .spawn(async move { build(previous.as_deref(), &snapshot, items, prepare, &server) }),
+    );
+}
+
+/// Builds the registry, running the [PrepareRegistry] hook once
+fn build<R: PluginRegistry>(
+    previous: Option<&R::Registry>,
+    old_snapshot: &ItemSnapshot<R>,
+    items: Vec<(PathIdentifier, R::Item)>,
+    prepare: Option<PrepareRegistry<R>>,
+    server: &AssetServer,
+) -> BuildResult<R> {
+    let snapshot = snapshot::<R>(&items);
+    let changed = changed_items::<R>(old_snapshot, &snapshot);
+    let (mut partial, hot) = match fill(previous, items) {
+        Ok(filled) => filled,
+        Err(err) => {
+            return BuildResult {
+                result: Err(err),
+                hot: false,
+                changed,
+                snapshot,
+            }
+        }
+    };
+    let result = match prepare {
+        Some(prepare) => prepare(&mut partial, server),
+        None => Ok(()),
+    }
+    .and_then(|()| partial.into_registry());
+    BuildResult {
+        result,
+        hot,
+        changed,
+        snapshot,
+    }
+}
+
+/// Inserts the items into a new partial registry, reserving the ids of the
+/// previous registry unless some of its items were removed
+fn fill<R: PluginRegistry>(
+    previous: Option<&R::Registry>,
+    items: Vec<(PathIdentifier, R::Item)>,
+) -> Result<(R, bool), DeserializationError<R>> {
+    let name = type_name::<R::Registry>();
+    if let Some(previous) = previous {
+        let mut partial = R::default();
+        match partial.reserve_ids(previous) {
+            Ok(()) => {
+                insert_items(&mut partial, items.iter().cloned())?;
+                // Every inserted item fills a single slot, the rest are
+                // reserved slots of the removed items
+                let slots: usize = partial.item_counts().iter().map(|(_, count)| count).sum();
+                debug_assert!(slots >= items.len(), "Inserted items should fill slots");
+                let removed = slots.saturating_sub(items.len());
+                if removed == 0 {
+                    return Ok((partial, true));
+                }
+                bevy_log::info!("{removed} items of {name} were removed, rebuilding from scratch");
+            }
+            Err(err) => {
+                bevy_log::warn!("Failed to reserve ids of {name}, rebuilding from scratch:\n{err}");
+            }
+        }
+    }
+
+    let mut partial = R::default();
+    insert_items(&mut partial, items)?;
+    Ok((partial, false))
+}
+
+/// Inserts the items, stopping at the first error since the registry is
+/// poisoned by it
+fn insert_items<R: LoadableRegistry>(
+    partial: &mut R,
+    items: impl IntoIterator<Item = (PathIdentifier, R::Item)>,
+) -> Result<(), DeserializationError<R>> {
+    items
+        .into_iter()
+        .try_for_each(|(path, item)| partial.insert(path, item))
+}
+
+/// Replaces the registry resource with the built one
+fn finish_build<R: PluginRegistry>(
+    mut commands: Commands,
+    mut loader: ResMut<RegistryLoader<R>>,
+    registry: Option<ResMut<LoadedRegistry<R::Registry>>>,
+    mut reloaded: EventWriter<RegistryReloaded<R>>,
+) {
+    if !loader.task.as_ref().is_some_and(Task::is_finished) {
+        return;
+    }
+    let task = loader.task.take().expect("Task was checked to be present");
+    let BuildResult {
+        result,
+        hot,
+        changed,
+        snapshot,
+    } = block_on(task);
+    let new = match result {
+        Ok(new) => new,
+        Err(err) => {
+            bevy_log::error!("Failed to load {}:\n{err}", type_name::<R::Registry>());
+            return;
+        }
+    };
+
+    match registry {
+        None => {
+            bevy_log::info!("Loaded {}", type_name::<R::Registry>());
+            commands.insert_resource(LoadedRegistry(Arc::new(new)));
+            loader.snapshot = Arc::new(snapshot);
+        }
+        Some(mut registry) => {
+            bevy_log::info!(
+                "Reloaded {}, {} items changed",
+                type_name::<R::Registry>(),
+                changed.len()
+            );
+            registry.0 = Arc::new(new);
+            loader.snapshot = Arc::new(snapshot);
+            reloaded.send(RegistryReloaded {
+                changed,
+                ids_changed: !hot,
+            });
+        }
+    }
+}
+
+/// Snapshot of the serialized items, compared between builds
+fn snapshot<R: PluginRegistry>(items: &[(PathIdentifier, R::Item)]) -> ItemSnapshot<R> {
+    items
+        .iter()
+        .map(|(_, item)| {
+            let (kind, key) = R::item_key(item);
+            ((kind.to_string(), key), (kind, format!("{item:?}")))
+        })
+        .collect()
+}
+
+/// Items that were added, removed, or whose serialized form changed, in the
+/// snapshot order
+fn changed_items<R: PluginRegistry>(
+    old: &ItemSnapshot<R>,
+    new: &ItemSnapshot<R>,
+) -> Vec<(R::ItemKind, Option<ItemId>)> {
+    let mut changed: Vec<_> = new
+        .iter()
+        .filter(|(key, (_, item))| old.get(*key).map(|(_, old)| old) != Some(item))
+        .chain(old.iter().filter(|(key, _)| !new.contains_key(*key)))
+        .map(|(key, (kind, _))| (key, kind))
+        .collect();
+    changed.sort_unstable_by_key(|(key, _)| *key);
+    changed
+        .into_iter()
+        .map(|((_, key), kind)| (kind.clone(), key.clone()))
+        .collect()
+}
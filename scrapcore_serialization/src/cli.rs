@@ -0,0 +1,162 @@
+//! Building blocks for content validator binaries
+//!
+//! A game can ship a validator for its content with a single function:
+//!
+//! ```ignore
+//! fn main() -> std::process::ExitCode {
+//!     scrapcore_serialization::cli::main::<PartialGameRegistry>("game-validator")
+//! }
+//! ```
+//!
+//! Errors are rendered with the installed `miette` report handler, so
+//! enabling `fancy` feature of `miette` in the binary gives graphical output
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use schemars::JsonSchema;
+
+use crate::registry::loader::{load_directory, load_registry};
+use crate::registry::LoadableRegistry;
+use crate::serialization::error::DeserializationError;
+
+/// Reason for the validator run to fail
+#[derive(Debug)]
+pub enum CliFailure {
+    /// Command line arguments are invalid, holds the explanation
+    Usage(String),
+    /// Content failed validation, holds the number of reported errors
+    Invalid(usize),
+    /// Output could not be written
+    Output(std::io::Error),
+}
+
+impl From<std::io::Error> for CliFailure {
+    fn from(err: std::io::Error) -> Self {
+        CliFailure::Output(err)
+    }
+}
+
+/// Runs the validator with the process arguments, printing results to the
+/// standard output, and errors and failures to the standard error
+///
+/// Exits with code 1 if the content is invalid, and with code 2 if the
+/// arguments are invalid
+pub fn main<Registry: LoadableRegistry>(name: &str) -> ExitCode
+where
+    Registry::Item: JsonSchema,
+{
+    let mut err = std::io::stderr().lock();
+    let result = run::<Registry>(
+        std::env::args_os().skip(1),
+        &mut std::io::stdout().lock(),
+        &mut err,
+    );
+    let (message, code) = match result {
+        Ok(()) => return ExitCode::SUCCESS,
+        Err(CliFailure::Usage(reason)) => (format!("{reason}\n\n{}", usage(name)), 2),
+        Err(CliFailure::Invalid(count)) => (format!("Validation failed with {count} error(s)"), 1),
+        Err(CliFailure::Output(err)) => (format!("Failed to write output: {err}"), 1),
+    };
+    // Nowhere left to report the failure if the standard error is closed
+    let _ = writeln!(err, "{message}");
+    ExitCode::from(code)
+}
+
+/// Usage help of the validator
+pub fn usage(name: &str) -> String {
+    format!(
+        "Usage:
+    {name} validate <DIR>   Loads the content directory and reports all errors
+    {name} stats <DIR>      Validates the content, and prints number of items of every kind
+    {name} schema [DIR]     Prints JSON schema of the content files, with references
+                            restricted to items and assets of DIR when provided"
+    )
+}
+
+/// Runs the validator command, writing results into `out` and reporting
+/// content errors into `err`
+pub fn run<Registry: LoadableRegistry>(
+    args: impl IntoIterator<Item = OsString>,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<(), CliFailure>
+where
+    Registry::Item: JsonSchema,
+{
+    let mut args = args.into_iter();
+    let command = args
+        .next()
+        .ok_or_else(|| CliFailure::Usage("Missing command".to_string()))?;
+    let dir = args.next().map(PathBuf::from);
+    if let Some(extra) = args.next() {
+        return Err(CliFailure::Usage(format!(
+            "Unexpected argument `{}`",
+            extra.to_string_lossy()
+        )));
+    }
+    let required_dir = || {
+        dir.clone()
+            .ok_or_else(|| CliFailure::Usage("Missing content directory".to_string()))
+    };
+
+    match command.to_string_lossy().as_ref() {
+        "validate" => {
+            load_registry::<Registry>(&required_dir()?).map_err(|errors| report(errors, err))?;
+            writeln!(out, "Content is valid")?;
+        }
+        "stats" => {
+            let (registry, errors) = load_directory::<Registry>(&required_dir()?);
+            if !errors.is_empty() {
+                return Err(report(errors, err));
+            }
+            let items = registry.item_counts();
+            let assets = registry.asset_counts();
+            registry
+                .into_registry()
+                .map_err(|error| report(vec![error], err))?;
+
+            for (kind, count) in items {
+                writeln!(out, "{kind}: {count}")?;
+            }
+            for (kind, count) in assets {
+                writeln!(out, "{kind} assets: {count}")?;
+            }
+        }
+        "schema" => {
+            let schema = match &dir {
+                None => Registry::default()
+                    .schema_references()
+                    .schema_for::<Registry::Item>(),
+                Some(dir) => {
+                    let (registry, errors) = load_directory::<Registry>(dir);
+                    if !errors.is_empty() {
+                        return Err(report(errors, err));
+                    }
+                    registry.schema_references().schema_for::<Registry::Item>()
+                }
+            };
+            let schema = serde_json::to_string_pretty(&schema)
+                .expect("Schema should always be serializable");
+            writeln!(out, "{schema}")?;
+        }
+        other => return Err(CliFailure::Usage(format!("Unknown command `{other}`"))),
+    }
+
+    Ok(())
+}
+
+/// Writes all errors into `err`
+fn report<Registry: LoadableRegistry>(
+    errors: Vec<DeserializationError<Registry>>,
+    err: &mut impl Write,
+) -> CliFailure {
+    let count = errors.len();
+    for error in errors {
+        if let Err(io) = writeln!(err, "{:?}", miette::Report::new(error.diagnostic())) {
+            return CliFailure::Output(io);
+        }
+    }
+    CliFailure::Invalid(count)
+}
@@ -0,0 +1,240 @@
+//! Interned strings used as item keys, see [InternedStr]
+use std::borrow::{Borrow, Cow};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use ahash::AHashSet;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Strings shared by all live [InternedStr]s
+static INTERNER: OnceLock<RwLock<AHashSet<Arc<str>>>> = OnceLock::new();
+
+fn interner() -> &'static RwLock<AHashSet<Arc<str>>> {
+    INTERNER.get_or_init(Default::default)
+}
+
+/// Immutable string stored once per process while in use
+///
+/// Cloning is a reference count increment, and equality is checked by
+/// comparing pointers. Hashing and ordering follow the string contents, so
+/// maps keyed by interned strings can be queried with plain `&str`s.
+///
+/// The string is removed from the interner once its last copy is dropped,
+/// so keys of removed items and keys seen only in failed loads don't stay
+/// allocated for the lifetime of the process
+#[derive(Clone)]
+pub struct InternedStr(Arc<str>);
+
+impl InternedStr {
+    /// Interns the string, reusing the existing allocation if the same string
+    /// is already interned
+    pub fn new(value: &str) -> Self {
+        if let Some(interned) = interner()
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(value)
+        {
+            return Self(interned.clone());
+        }
+
+        let mut interner = interner().write().unwrap_or_else(|err| err.into_inner());
+        // Another thread could've interned the string while the lock was
+        // released
+        if let Some(interned) = interner.get(value) {
+            return Self(interned.clone());
+        }
+        let interned: Arc<str> = value.into();
+        interner.insert(interned.clone());
+        Self(interned)
+    }
+
+    /// Whether any copy of the interned string is alive
+    pub fn is_interned(value: &str) -> bool {
+        interner()
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .contains(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for InternedStr {
+    fn drop(&mut self) {
+        // The interner holds the other reference of the last copy
+        if Arc::strong_count(&self.0) > 2 {
+            return;
+        }
+        let mut interner = interner().write().unwrap_or_else(|err| err.into_inner());
+        // New copies are only made from existing ones or under the lock, so
+        // the count can't grow while it's held
+        if Arc::strong_count(&self.0) == 2 {
+            interner.remove(&*self.0);
+        }
+    }
+}
+
+impl Default for InternedStr {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Deref for InternedStr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InternedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for InternedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for InternedStr {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for InternedStr {
+    fn from(value: String) -> Self {
+        Self::new(&value)
+    }
+}
+
+impl From<&String> for InternedStr {
+    fn from(value: &String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<Cow<'_, str>> for InternedStr {
+    fn from(value: Cow<'_, str>) -> Self {
+        Self::new(&value)
+    }
+}
+
+impl From<InternedStr> for String {
+    fn from(value: InternedStr) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl PartialEq for InternedStr {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InternedStr {}
+
+impl PartialEq<str> for InternedStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for InternedStr {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for InternedStr {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialOrd for InternedStr {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InternedStr {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Hash for InternedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Debug for InternedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Display for InternedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for InternedStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct InternedVisitor;
+
+        impl<'de> Visitor<'de> for InternedVisitor {
+            type Value = InternedStr;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            // Already interned strings are looked up without allocating
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(InternedStr::new(v))
+            }
+        }
+
+        deserializer.deserialize_str(InternedVisitor)
+    }
+}
+
+impl JsonSchema for InternedStr {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        String::schema_id()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
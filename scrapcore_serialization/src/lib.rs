@@ -1,13 +1,24 @@
+pub mod interned;
+
 pub mod registry;
 
 pub mod serialization;
 
+#[cfg(feature = "cli")]
+pub mod cli;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "bevy_plugin")]
+pub mod bevy_plugin;
+
 #[cfg(feature = "derive")]
 pub mod derive {
     pub use scrapcore_serialization_macro::*;
 }
 
-pub type ItemId = String;
+pub type ItemId = interned::InternedStr;
 pub type ItemIdRef<'a> = &'a str;
 pub type AssetName = String;
 pub type AssetNameRef<'a> = &'a str;
@@ -1,5 +1,8 @@
 use ahash::AHashMap;
-use slabmap::{SlabMap, SlabMapId};
+#[cfg(feature = "rayon")]
+use rayon::iter::ParallelIterator;
+use serde::de::DeserializeOwned;
+use slabmap::{SlabMap, SlabMapId, SlabMapUntypedId};
 use std::error::Error;
 use std::fmt::{Debug, Display};
 
@@ -7,7 +10,8 @@ use crate::registry::entry::{RegistryEntry, RegistryEntrySerialized};
 use crate::registry::kind::{AssetKindProvider, ItemKindProvider};
 use crate::registry::path_identifier::PathIdentifier;
 use crate::serialization::error::internal::InternalDeserializationError;
-use crate::serialization::error::DeserializationError;
+use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::schema::SchemaReferences;
 use crate::serialization::DeserializeModel;
 use crate::{AssetName, ItemId};
 
@@ -18,8 +22,12 @@ pub mod index;
 pub mod inline;
 pub mod insert;
 pub mod kind;
+#[cfg(feature = "loader")]
+pub mod loader;
 pub mod path_identifier;
+pub mod remap;
 pub mod reverse_id;
+pub mod variables;
 
 /// Collection of items in a registry
 pub type ItemCollection<T> = SlabMap<ItemId, RegistryEntry<T>>;
@@ -45,6 +53,34 @@ pub enum MaybeRawItem<T, Serialized> {
 pub type PartialItemCollection<T, Serialized> =
     SlabMap<ItemId, (PathIdentifier, MaybeRawItem<T, Serialized>)>;
 
+/// Collection item being deserialized, passed to `#[model(validate = ...)]`
+/// hooks
+///
+/// Models nested inside of other items, including inline items, get the
+/// context of the item containing them
+#[derive(Debug, Clone)]
+pub struct ItemContext<Kind> {
+    pub kind: Kind,
+    pub key: ItemId,
+}
+
+impl<Kind: Display> ItemContext<Kind> {
+    /// Id of the item, or `None` if the item is not a part of the collection
+    /// of `T`
+    pub fn id_of<T, Registry: PartialCollectionHolder<T, ItemKind = Kind>>(
+        &self,
+        registry: &mut Registry,
+    ) -> Option<CollectionItemId<T>> {
+        // Kinds are told apart by their names, which doesn't require item
+        // kinds to implement `Eq`
+        if self.kind.to_string() != <Registry as ItemKindProvider<T>>::kind().to_string() {
+            return None;
+        }
+        let id = registry.get_collection().key_to_id(&self.key)?;
+        Some(id.as_untyped().as_typed_unchecked())
+    }
+}
+
 /// Singleton item in a registry
 pub type Singleton<T> = T;
 
@@ -64,6 +100,44 @@ pub type AssetsCollection<T> = AHashMap<AssetName, (T, PathIdentifier)>;
 pub trait CollectionHolder<Value>: SerializationRegistry + ItemKindProvider<Value> {
     fn get_collection(&self) -> &ItemCollection<Value>;
     fn get_collection_mut(&mut self) -> &mut ItemCollection<Value>;
+
+    /// Iterates over all items of the collection in parallel
+    #[cfg(feature = "rayon")]
+    fn par_iter_items<'a>(
+        &'a self,
+    ) -> impl ParallelIterator<Item = (CollectionItemId<Value>, &'a Value)>
+    where
+        Value: Sync + 'a,
+    {
+        self.get_collection()
+            .par_iter()
+            .map(|(id, entry)| (id, &entry.data))
+    }
+
+    /// Same as [Self::par_iter_items], without item ids
+    #[cfg(feature = "rayon")]
+    fn par_items<'a>(&'a self) -> impl ParallelIterator<Item = &'a Value>
+    where
+        Value: Sync + 'a,
+    {
+        self.get_collection()
+            .par_iter()
+            .map(|(_, entry)| &entry.data)
+    }
+
+    /// Iterates over all items of the collection in parallel, allowing them
+    /// to be modified in place
+    #[cfg(feature = "rayon")]
+    fn par_iter_items_mut<'a>(
+        &'a mut self,
+    ) -> impl ParallelIterator<Item = (CollectionItemId<Value>, &'a mut Value)>
+    where
+        Value: Send + 'a,
+    {
+        self.get_collection_mut()
+            .par_iter_mut()
+            .map(|(id, entry)| (id, &mut entry.data))
+    }
 }
 
 /// Registry trait for singletons
@@ -120,6 +194,80 @@ pub trait PartialRegistry: SerializationRegistry {
 
     /// Determines whenever registry is poisoned
     fn is_poisoned(&self) -> bool;
+
+    /// Collection item currently being deserialized, `None` outside of
+    /// collection items
+    ///
+    /// Registries that don't track the current item always return `None`
+    fn current_item(&self) -> Option<ItemContext<Self::ItemKind>> {
+        None
+    }
+
+    /// Replaces the current item, returning the previous one
+    fn set_current_item(
+        &mut self,
+        item: Option<ItemContext<Self::ItemKind>>,
+    ) -> Option<ItemContext<Self::ItemKind>> {
+        let _ = item;
+        None
+    }
+
+    /// Resolves the value of the expression variable
+    ///
+    /// Registries without a designated variables storage (see
+    /// [variables]) report all variables as missing
+    fn get_variable(&mut self, name: &str) -> Result<f64, DeserializationError<Self>>
+    where
+        Self: Sized,
+    {
+        Err(DeserializationErrorKind::MissingVariable(name.to_string()).into())
+    }
+}
+
+/// Partial registry that can be filled with serialized items and finalized
+/// by generic code, implemented by `#[registry]` macro
+pub trait LoadableRegistry: PartialRegistry + Default {
+    /// Serialized registry item
+    type Item: DeserializeOwned + Debug;
+
+    /// Inserts a raw item into the registry
+    fn insert(
+        &mut self,
+        path: impl Into<PathIdentifier>,
+        item: Self::Item,
+    ) -> Result<(), DeserializationError<Self>>;
+
+    /// Deserializes all inserted items, producing the complete registry
+    fn into_registry(self) -> Result<Self::Registry, DeserializationError<Self>>;
+
+    /// Item keys and asset names currently present in the registry
+    fn schema_references(&self) -> SchemaReferences;
+
+    /// Kind of the serialized item, paired with its key for collection items
+    fn item_key(item: &Self::Item) -> (Self::ItemKind, Option<ItemId>);
+
+    /// Number of inserted items of every item kind
+    fn item_counts(&self) -> Vec<(Self::ItemKind, usize)>;
+
+    /// Number of assets of every asset kind
+    fn asset_counts(&self) -> Vec<(Self::AssetKind, usize)>;
+}
+
+/// Callback of [RegistryItems::visit_items], receiving the item kind, the
+/// key for collection items, and the item itself
+pub type ItemVisitor<'a, Kind> = dyn FnMut(Kind, Option<&ItemId>, &dyn Debug) + 'a;
+
+/// Read-only access to all items of a complete registry, implemented by
+/// `#[registry]` macro
+pub trait RegistryItems: SerializationRegistry {
+    /// Calls `visit` for every item in the registry, with collection items
+    /// being paired with their keys
+    fn visit_items(&self, visit: &mut ItemVisitor<'_, Self::ItemKind>);
+
+    /// Key of the collection item with the given id, with the collection
+    /// being picked by the type name of its values (as in
+    /// [std::any::type_name])
+    fn id_to_key(&self, value_type: &str, id: SlabMapUntypedId) -> Option<&ItemId>;
 }
 
 /// Runs the closure on the partial registry, returning the error if registry
@@ -101,7 +101,12 @@ where
             return Ok(());
         };
 
-        let deserialized = item.deserialize(registry).map_err(|e| {
+        // Singletons can be deserialized on demand while processing a
+        // collection item, which shouldn't leak into singleton validators
+        let previous = registry.set_current_item(None);
+        let deserialized = item.deserialize(registry);
+        registry.set_current_item(previous);
+        let deserialized = deserialized.map_err(|e| {
             e.context(DeserializationErrorStackItem::ItemByPath(
                 path.to_owned(),
                 Registry::kind(),
@@ -1,9 +1,15 @@
 use crate::registry::index::RegistryIndex;
 use crate::registry::{CollectionHolder, CollectionItemId, PartialRegistry, SerializationRegistry};
 use crate::serialization::error::DeserializationError;
+use crate::serialization::schema::ItemReference;
 use crate::serialization::{DeserializeModel, SerializationFallback};
-use crate::ItemId;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::borrow::Cow;
+use std::fmt::{Debug, Formatter};
 
 /// Item that can either be referenced by ID or have [Data] inline
 #[derive(Debug, Clone)]
@@ -13,20 +19,72 @@ pub enum InlineOrId<Data> {
 }
 
 /// Serialized form of [InlineOrId]
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+///
+/// `Data` is only used to mark the referenced item kind in the schema
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(untagged)]
-pub enum InlineOrIdSerialized<DataSerialized> {
-    Id(ItemId),
+#[serde(bound(
+    serialize = "DataSerialized: Serialize",
+    deserialize = "DataSerialized: Deserialize<'de>"
+))]
+pub enum InlineOrIdSerialized<DataSerialized, Data> {
+    Id(ItemReference<Data>),
     Inline(DataSerialized),
 }
 
+impl<DataSerialized: Debug, Data> Debug for InlineOrIdSerialized<DataSerialized, Data> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InlineOrIdSerialized::Id(id) => f.debug_tuple("Id").field(id).finish(),
+            InlineOrIdSerialized::Inline(data) => f.debug_tuple("Inline").field(data).finish(),
+        }
+    }
+}
+
+impl<DataSerialized: Clone, Data> Clone for InlineOrIdSerialized<DataSerialized, Data> {
+    fn clone(&self) -> Self {
+        match self {
+            InlineOrIdSerialized::Id(id) => InlineOrIdSerialized::Id(id.clone()),
+            InlineOrIdSerialized::Inline(data) => InlineOrIdSerialized::Inline(data.clone()),
+        }
+    }
+}
+
+impl<DataSerialized: JsonSchema, Data> JsonSchema for InlineOrIdSerialized<DataSerialized, Data> {
+    fn schema_name() -> String {
+        format!("InlineOrId_for_{}", DataSerialized::schema_name())
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        Cow::Owned(format!(
+            "InlineOrIdSerialized<{}, {}>",
+            DataSerialized::schema_id(),
+            type_name::<Data>()
+        ))
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<ItemReference<Data>>(),
+                    gen.subschema_for::<DataSerialized>(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl<Data: SerializationFallback> SerializationFallback for InlineOrId<Data> {
-    type Fallback = InlineOrIdSerialized<Data::Fallback>;
+    type Fallback = InlineOrIdSerialized<Data::Fallback, Data>;
 }
 
 impl<Registry: PartialRegistry, Data, DataSerialized: DeserializeModel<Data, Registry>>
-    DeserializeModel<InlineOrId<Data>, Registry> for InlineOrIdSerialized<DataSerialized>
+    DeserializeModel<InlineOrId<Data>, Registry> for InlineOrIdSerialized<DataSerialized, Data>
 where
     for<'a> &'a str: DeserializeModel<CollectionItemId<Data>, Registry>,
 {
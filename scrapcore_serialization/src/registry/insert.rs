@@ -1,5 +1,7 @@
 use std::collections::hash_map::Entry;
 
+use slabmap::entry::Entry as SlabMapEntry;
+
 use crate::registry::entry::RegistryEntrySerialized;
 use crate::registry::path_identifier::PathIdentifier;
 use crate::registry::{
@@ -20,15 +22,18 @@ pub fn registry_insert<T, Registry: PartialCollectionHolder<T>>(
     poison_on_err(registry, |registry| {
         let path = path.into();
         let raw = registry.get_collection();
-        if let Some(entry) = raw.get_by_key_mut(&item.id) {
+        match raw.entry(item.id.clone()) {
             // Hot reloading slots can be safely replaced
-            if matches!(entry.1, MaybeRawItem::HotReloading) {
-                *entry = (path, MaybeRawItem::Raw(item));
-            } else {
+            SlabMapEntry::Occupied(mut entry)
+                if matches!(entry.get().1, MaybeRawItem::HotReloading) =>
+            {
+                entry.insert((path, MaybeRawItem::Raw(item)));
+            }
+            SlabMapEntry::Occupied(entry) => {
                 return Err(DeserializationErrorKind::DuplicateItem {
-                    id: item.id.clone(),
+                    id: item.id,
                     kind: Registry::kind(),
-                    path_a: entry.0.clone(),
+                    path_a: entry.get().0.clone(),
                     path_b: path.clone(),
                 }
                 .into_err()
@@ -37,8 +42,9 @@ pub fn registry_insert<T, Registry: PartialCollectionHolder<T>>(
                     Registry::kind(),
                 )));
             }
-        } else {
-            raw.insert(item.id.clone(), (path, MaybeRawItem::Raw(item)));
+            SlabMapEntry::Vacant(entry) => {
+                entry.insert((path, MaybeRawItem::Raw(item)));
+            }
         }
         Ok(())
     })
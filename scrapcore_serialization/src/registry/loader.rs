@@ -0,0 +1,93 @@
+//! Loading of registry items from directories of JSON files
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+use crate::registry::path_identifier::PathIdentifier;
+use crate::registry::{LoadableRegistry, PartialRegistry};
+use crate::serialization::error::{
+    DeserializationError, DeserializationErrorKind, DeserializationErrorStackItem,
+};
+
+/// Loads all `.json` files in the directory and its subdirectories into a
+/// new partial registry
+///
+/// Files are visited in the order of their names, with item paths being
+/// relative to `root`. Files that fail to be read or parsed don't stop the
+/// loading, and errors for all of them are returned alongside the registry.
+/// Once an insertion error poisons the registry, remaining files are only
+/// parsed
+pub fn load_directory<Registry: LoadableRegistry>(
+    root: &Path,
+) -> (Registry, Vec<DeserializationError<Registry>>) {
+    let mut registry = Registry::default();
+    let mut errors = vec![];
+
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                let path = err.path().unwrap_or(root).to_path_buf();
+                errors.push(loading_error(root, &path, err));
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension() != Some("json".as_ref()) {
+            continue;
+        }
+
+        let item = std::fs::read(path)
+            .map_err(|err| loading_error(root, path, err))
+            .and_then(|data| {
+                serde_json::from_slice::<Registry::Item>(&data)
+                    .map_err(|err| loading_error(root, path, err))
+            });
+        let item = match item {
+            Ok(item) => item,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+
+        if registry.is_poisoned() {
+            continue;
+        }
+        if let Err(err) = registry.insert(relative_path(root, path), item) {
+            errors.push(err);
+        }
+    }
+
+    (registry, errors)
+}
+
+/// Loads the directory and deserializes the complete registry
+///
+/// Returns all errors from [load_directory], or the finalization error if
+/// all files were loaded successfully
+pub fn load_registry<Registry: LoadableRegistry>(
+    root: &Path,
+) -> Result<Registry::Registry, Vec<DeserializationError<Registry>>> {
+    let (registry, errors) = load_directory::<Registry>(root);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    registry.into_registry().map_err(|err| vec![err])
+}
+
+fn relative_path(root: &Path, path: &Path) -> PathIdentifier {
+    path.strip_prefix(root).unwrap_or(path).into()
+}
+
+fn loading_error<Registry: PartialRegistry>(
+    root: &Path,
+    path: &Path,
+    err: impl ToString,
+) -> DeserializationError<Registry> {
+    DeserializationErrorKind::LoadingError(err.to_string())
+        .into_err()
+        .context(DeserializationErrorStackItem::File(relative_path(
+            root, path,
+        )))
+}
@@ -4,7 +4,9 @@ use std::path::{Path, PathBuf};
 
 /// Opaque identifier for a path to be used in registry
 ///
-/// Mostly used for error reporting, and for looking up assets by their name
+/// Mostly used for error reporting, and for looking up assets by their name.
+/// Components are plain strings rather than interned ones, since file names
+/// and bundle indices are rarely shared and would otherwise never be freed
 #[derive(Debug, Clone)]
 pub struct PathIdentifier {
     components: Vec<String>,
@@ -0,0 +1,250 @@
+//! Rebuilding of item collections, with ids stored inside items being
+//! updated to match the rebuilt collections
+//!
+//! Models marked with `#[model(remap_ids)]` and registries marked with
+//! `#[registry(remap_ids)]` implement [RemapIds], allowing collections of the
+//! complete registry to be compacted or reordered:
+//!
+//! ```ignore
+//! let remap = sort_collection::<Person, _, _>(&mut registry, |key, _| *key)?;
+//! // Ids stored outside of the registry can be updated with the same table
+//! let mayor = remap.remap_id(mayor);
+//! ```
+//!
+//! Ids that were left dangling by removing items have no new id. Optional
+//! ids are cleared, and other dangling ids fail the remapping with
+//! [DanglingIdError]
+use std::any::{type_name, Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use duplicate::duplicate_item;
+use slabmap::SlabMapRemap;
+use thiserror::Error;
+
+use crate::registry::entry::RegistryEntry;
+use crate::registry::inline::InlineOrId;
+use crate::registry::{CollectionHolder, CollectionItemId};
+use crate::ItemId;
+
+/// Remap tables of the rebuilt collections, keyed by the collection item type
+#[derive(Debug, Default)]
+pub struct RegistryRemap {
+    tables: AHashMap<TypeId, Box<dyn Any>>,
+}
+
+impl RegistryRemap {
+    /// Adds the remap table of the collection of items of type `T`,
+    /// replacing the previous one
+    pub fn insert<T: 'static>(&mut self, remap: SlabMapRemap<RegistryEntry<T>>) {
+        self.tables.insert(TypeId::of::<T>(), Box::new(remap));
+    }
+
+    /// Remap table of the collection of items of type `T`, if it was rebuilt
+    pub fn get<T: 'static>(&self) -> Option<&SlabMapRemap<RegistryEntry<T>>> {
+        self.tables
+            .get(&TypeId::of::<T>())
+            .and_then(|table| table.downcast_ref())
+    }
+
+    /// New id of the item
+    ///
+    /// Ids of collections that were not rebuilt are returned as is. Ids of
+    /// rebuilt collections that were not pointing to an item before
+    /// rebuilding have no new id
+    pub fn remap_id<T: 'static>(&self, id: CollectionItemId<T>) -> Option<CollectionItemId<T>> {
+        match self.get::<T>() {
+            None => Some(id),
+            Some(table) => table.get(id),
+        }
+    }
+}
+
+/// Id of the rebuilt collection was not pointing to an item before
+/// rebuilding, and is not stored in an `Option` that could be cleared
+#[derive(Debug, Clone, Error)]
+#[error("Dangling id {index} of `{kind}` item has no new id")]
+pub struct DanglingIdError {
+    /// Item type of the collection
+    pub kind: &'static str,
+    /// Raw index of the dangling id
+    pub index: usize,
+}
+
+/// Values holding collection item ids, which can be updated after the
+/// collections are rebuilt
+pub trait RemapIds {
+    /// Replaces all ids held by the value with the new ones from `remap`
+    ///
+    /// Fails on the first id with no new id, leaving the value partially
+    /// remapped
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError>;
+
+    /// Remaps the value stored in an `Option`
+    ///
+    /// Ids override this to clear the `Option` when they have no new id
+    fn remap_option(value: &mut Option<Self>, remap: &RegistryRemap) -> Result<(), DanglingIdError>
+    where
+        Self: Sized,
+    {
+        match value {
+            Some(value) => value.remap_ids(remap),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: 'static> RemapIds for CollectionItemId<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        *self = remap.remap_id(*self).ok_or_else(|| DanglingIdError {
+            kind: type_name::<T>(),
+            index: self.raw(),
+        })?;
+        Ok(())
+    }
+
+    fn remap_option(
+        value: &mut Option<Self>,
+        remap: &RegistryRemap,
+    ) -> Result<(), DanglingIdError> {
+        if let Some(id) = value {
+            *value = remap.remap_id(*id);
+        }
+        Ok(())
+    }
+}
+
+impl<T: RemapIds + 'static> RemapIds for RegistryEntry<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        self.id.remap_ids(remap)?;
+        self.data.remap_ids(remap)
+    }
+}
+
+impl<T: RemapIds + 'static> RemapIds for InlineOrId<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        match self {
+            InlineOrId::Id(id) => id.remap_ids(remap),
+            InlineOrId::Inline(data) => data.remap_ids(remap),
+        }
+    }
+}
+
+#[duplicate_item(
+    ty;
+    [i8]; [i16]; [i32]; [i64]; [i128];
+    [u8]; [u16]; [u32]; [u64]; [u128];
+    [f32]; [f64];
+    [bool]; [String];
+)]
+impl RemapIds for ty {
+    #[inline(always)]
+    fn remap_ids(&mut self, _remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        Ok(())
+    }
+}
+
+impl<T: RemapIds> RemapIds for Option<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        T::remap_option(self, remap)
+    }
+}
+
+impl<T: RemapIds> RemapIds for Vec<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        self.iter_mut().try_for_each(|value| value.remap_ids(remap))
+    }
+}
+
+impl<T: RemapIds> RemapIds for Box<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        T::remap_ids(self, remap)
+    }
+}
+
+/// Shared values are cloned before remapping
+impl<T: RemapIds + Clone> RemapIds for Arc<T> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        Arc::make_mut(self).remap_ids(remap)
+    }
+}
+
+/// Sets are rebuilt, since remapping changes hashes of the values
+impl<T: RemapIds + Eq + Hash, S: BuildHasher + Default> RemapIds for HashSet<T, S> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        *self = std::mem::take(self)
+            .into_iter()
+            .map(|mut value| {
+                value.remap_ids(remap)?;
+                Ok(value)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+}
+
+/// Maps are rebuilt, since remapping changes hashes of the keys
+impl<K: RemapIds + Eq + Hash, V: RemapIds, S: BuildHasher + Default> RemapIds for HashMap<K, V, S> {
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        *self = std::mem::take(self)
+            .into_iter()
+            .map(|(mut key, mut value)| {
+                key.remap_ids(remap)?;
+                value.remap_ids(remap)?;
+                Ok((key, value))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+}
+
+/// Compacts the collection of items of type `T`, and remaps ids stored
+/// inside the registry items
+///
+/// Returns the remap table for updating ids stored outside of the registry
+///
+/// Fails if the registry holds a dangling id that can't be cleared, the
+/// registry is left partially remapped in that case
+pub fn compact_collection<T, Registry>(
+    registry: &mut Registry,
+) -> Result<RegistryRemap, DanglingIdError>
+where
+    T: 'static,
+    Registry: CollectionHolder<T> + RemapIds,
+{
+    let table = registry.get_collection_mut().compact();
+    apply_remap::<T, _>(registry, table)
+}
+
+/// Reorders the collection of items of type `T` by the result of `f`, and
+/// remaps ids stored inside the registry items
+///
+/// Returns the remap table for updating ids stored outside of the registry,
+/// fails same as [compact_collection]
+pub fn sort_collection<T, Registry, O>(
+    registry: &mut Registry,
+    mut f: impl FnMut(&ItemId, &T) -> O,
+) -> Result<RegistryRemap, DanglingIdError>
+where
+    T: 'static,
+    Registry: CollectionHolder<T> + RemapIds,
+    O: Ord,
+{
+    let table = registry
+        .get_collection_mut()
+        .sort_by_key(|key, entry| f(key, &entry.data));
+    apply_remap::<T, _>(registry, table)
+}
+
+fn apply_remap<T: 'static, Registry: RemapIds>(
+    registry: &mut Registry,
+    table: SlabMapRemap<RegistryEntry<T>>,
+) -> Result<RegistryRemap, DanglingIdError> {
+    let mut remap = RegistryRemap::default();
+    // Identity table still clears ids left dangling by removed items
+    remap.insert(table);
+    registry.remap_ids(&remap)?;
+    Ok(remap)
+}
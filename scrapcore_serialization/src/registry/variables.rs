@@ -0,0 +1,107 @@
+//! Lookup of the expression variables, used by
+//! [crate::serialization::expr::NumericExpr]
+//!
+//! Variables can be stored either in a collection, with item IDs acting as
+//! variable names, or in a singleton holding a set of named values. Registries
+//! pick the storage by implementing
+//! [crate::registry::PartialRegistry::get_variable] via
+//! [collection_variable] or [singleton_variable]
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+use crate::registry::finalize::process_raw_singleton;
+use crate::registry::kind::ItemKindProvider;
+use crate::registry::{
+    CollectionItemId, MaybeRawItem, MaybeRawSingleton, PartialCollectionHolder,
+    PartialSingletonHolder,
+};
+use crate::serialization::error::internal::InternalDeserializationError;
+use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::expr::ExprValue;
+use crate::serialization::{DeserializeModel, SerializationFallback};
+
+/// Collection item acting as a single expression variable
+pub trait ExprVariableValue {
+    fn variable_value(&self) -> f64;
+}
+
+/// Singleton item holding a set of named expression variables
+pub trait ExprVariableSet {
+    fn variable(&self, name: &str) -> Option<f64>;
+}
+
+impl<K: Borrow<str> + Eq + Hash, V: ExprValue + Copy, S: BuildHasher> ExprVariableSet
+    for HashMap<K, V, S>
+{
+    fn variable(&self, name: &str) -> Option<f64> {
+        self.get(name).map(|v| v.into_expr_value())
+    }
+}
+
+/// Resolves a variable from the collection, deserializing the item on demand
+pub fn collection_variable<T: ExprVariableValue, Registry: PartialCollectionHolder<T>>(
+    registry: &mut Registry,
+    name: &str,
+) -> Result<f64, DeserializationError<Registry>> {
+    if !registry.get_collection().contains_key(name) {
+        return Err(DeserializationErrorKind::MissingVariable(name.to_string()).into());
+    }
+    let id: CollectionItemId<T> = name.deserialize(registry)?;
+
+    let (path, item) = &registry.get_collection()[id.as_untyped().as_typed_unchecked()];
+    match item {
+        MaybeRawItem::Deserialized(item) => Ok(item.data.variable_value()),
+        MaybeRawItem::Reserved(_) => {
+            Err(DeserializationErrorKind::RecursiveVariable(name.to_string()).into())
+        }
+        MaybeRawItem::Raw(_) => {
+            Err(InternalDeserializationError::EntryBecameRaw(path.clone(), Registry::kind()).into())
+        }
+        MaybeRawItem::HotReloading => Err(
+            InternalDeserializationError::EntryBecameUnfilledHotReloadingSlot(
+                path.clone(),
+                Registry::kind(),
+            )
+            .into(),
+        ),
+    }
+}
+
+/// Resolves a variable from the singleton, deserializing the singleton on
+/// demand
+///
+/// Singleton is taken out of the registry while being deserialized, so
+/// variables referenced from the singleton itself are reported as a missing
+/// singleton
+pub fn singleton_variable<
+    T: SerializationFallback + ExprVariableSet,
+    Registry: PartialSingletonHolder<T>,
+>(
+    registry: &mut Registry,
+    name: &str,
+) -> Result<f64, DeserializationError<Registry>>
+where
+    T::Fallback: DeserializeModel<T, Registry>,
+{
+    if let Some((_, MaybeRawSingleton::Raw(_))) = registry.get_singleton() {
+        process_raw_singleton::<T, Registry>(registry)?;
+    }
+
+    match registry.get_singleton() {
+        Some((_, MaybeRawSingleton::Deserialized(set))) => set
+            .variable(name)
+            .ok_or_else(|| DeserializationErrorKind::MissingVariable(name.to_string()).into()),
+        Some((path, MaybeRawSingleton::Raw(_))) => Err(
+            InternalDeserializationError::ConversionUnprocessedSingleton(
+                path.clone(),
+                <Registry as ItemKindProvider<T>>::kind(),
+            )
+            .into(),
+        ),
+        None => Err(DeserializationErrorKind::MissingSingleton {
+            kind: <Registry as ItemKindProvider<T>>::kind(),
+        }
+        .into()),
+    }
+}
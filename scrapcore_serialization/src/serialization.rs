@@ -4,27 +4,35 @@ use std::hash::{BuildHasher, Hash};
 
 use ahash::AHashMap;
 
-use slabmap::SlabMapId;
-
 use crate::registry::entry::{RegistryEntry, RegistryEntrySerialized};
 use crate::registry::{
-    poison_on_err, CollectionItemId, MaybeRawItem, PartialCollectionHolder, PartialRegistry,
+    poison_on_err, CollectionItemId, ItemContext, MaybeRawItem, PartialCollectionHolder,
+    PartialRegistry,
 };
 use crate::serialization::error::internal::InternalDeserializationError;
 use crate::serialization::error::{
     DeserializationError, DeserializationErrorKind, DeserializationErrorStackItem,
 };
-use crate::{ItemId, ItemIdRef};
+use crate::serialization::schema::ItemReference;
+use crate::ItemIdRef;
 
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
 pub mod box_wrapper;
+pub mod constant;
 pub mod error;
+pub mod expr;
 
 pub mod min_max;
+pub mod validators;
 
 pub mod helpers;
 
 pub mod primitives;
+pub mod schema;
 pub mod string;
+pub mod strong;
+pub mod versioned;
 
 #[cfg(feature = "bevy")]
 pub mod bevy;
@@ -130,8 +138,8 @@ impl<Key: SerializationFallback, Value: SerializationFallback, Hasher: BuildHash
 
 // endregion
 
-impl<Item> SerializationFallback for SlabMapId<Item> {
-    type Fallback = ItemId;
+impl<Data> SerializationFallback for CollectionItemId<Data> {
+    type Fallback = ItemReference<Data>;
 }
 
 impl<Registry: PartialRegistry, T> DeserializeModel<T, Registry> for String
@@ -154,17 +162,14 @@ impl<'a, Registry: PartialCollectionHolder<Data>, Data>
             let items = registry.get_collection();
 
             let id = items.key_to_id(self).ok_or_else(|| {
-                DeserializationErrorKind::<Registry>::MissingItem(
-                    self.to_string(),
-                    Registry::kind(),
-                )
+                DeserializationErrorKind::<Registry>::MissingItem(self.into(), Registry::kind())
             })?;
             let (_, item) = &mut items[id];
 
             let other = match item {
                 MaybeRawItem::HotReloading => {
                     return Err(InternalDeserializationError::UnfilledHotReloadingSlot(
-                        self.to_owned(),
+                        self.into(),
                         Registry::kind(),
                     )
                     .into());
@@ -233,7 +238,13 @@ impl<Registry: PartialCollectionHolder<Data>, Data>
                     .into_err())
                 }
             };
-            let data = DeserializeModel::<Data, Registry>::deserialize(self.data, registry)?;
+            let previous = registry.set_current_item(Some(ItemContext {
+                kind: Registry::kind(),
+                key: self.id.clone(),
+            }));
+            let data = DeserializeModel::<Data, Registry>::deserialize(self.data, registry);
+            registry.set_current_item(previous);
+            let data = data?;
             let model = RegistryEntry { id: model_id, data };
 
             let items = registry.get_collection();
@@ -0,0 +1,321 @@
+//! Property-based generation of serialized models
+//!
+//! Models marked with `#[model(arbitrary)]` and registries marked with
+//! `#[registry(arbitrary)]` implement [ArbitrarySerialized], producing
+//! `proptest` strategies for their serialized forms. Generated references
+//! point to keys from the provided [KeyPool], with occasional unknown keys
+//! mixed in to exercise missing item errors
+//!
+//! [check_registry] uses those strategies to fuzz the deserialization of the
+//! whole registry:
+//!
+//! ```ignore
+//! #[test]
+//! fn finalization_never_panics() {
+//!     let mut pool = KeyPool::default();
+//!     pool.add_items::<Person>(["test:alex", "test:sam"]);
+//!     check_registry::<PartialGameRegistry>(&pool, 256);
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::path::PathBuf;
+
+use ahash::AHashMap;
+use proptest::collection::{hash_map, vec};
+use proptest::option::weighted;
+use proptest::prelude::{any, prop_oneof};
+use proptest::sample::select;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+pub use proptest::strategy::{BoxedStrategy, Just, Strategy, Union};
+
+use crate::registry::entry::RegistryEntrySerialized;
+use crate::registry::inline::InlineOrIdSerialized;
+use crate::registry::LoadableRegistry;
+use crate::serialization::box_wrapper::SerializationBoxingWrapper;
+use crate::serialization::constant::ConstOr;
+use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::expr::NumericExpr;
+use crate::serialization::schema::{type_key, AssetReference, ItemReference};
+use crate::serialization::string::SerializationStringWrapper;
+use crate::{AssetName, ItemId};
+
+/// Maximum number of elements in generated collections
+const MAX_COLLECTION_LEN: usize = 4;
+/// Maximum number of items in generated registries
+const MAX_REGISTRY_ITEMS: usize = 16;
+
+/// Serialized value that can be generated for property-based testing
+pub trait ArbitrarySerialized: Sized + Debug {
+    /// Strategy generating values with references to the keys of the pool
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self>;
+}
+
+/// Item keys, asset names, and variable names used by the generated
+/// references, with item keys and asset names grouped by their type keys
+#[derive(Debug, Clone, Default)]
+pub struct KeyPool {
+    items: AHashMap<&'static str, Vec<ItemId>>,
+    assets: AHashMap<&'static str, Vec<AssetName>>,
+    variables: Vec<String>,
+}
+
+impl KeyPool {
+    /// Adds keys of items of type `T`
+    pub fn add_items<T>(&mut self, keys: impl IntoIterator<Item = impl Into<ItemId>>) {
+        self.items
+            .entry(type_key::<T>())
+            .or_default()
+            .extend(keys.into_iter().map(Into::into))
+    }
+
+    /// Adds names of assets of type `T`
+    pub fn add_assets<T>(&mut self, names: impl IntoIterator<Item = impl Into<AssetName>>) {
+        self.assets
+            .entry(type_key::<T>())
+            .or_default()
+            .extend(names.into_iter().map(Into::into))
+    }
+
+    /// Adds names of expression variables and constants
+    pub fn add_variables(&mut self, names: impl IntoIterator<Item = impl Into<String>>) {
+        self.variables.extend(names.into_iter().map(Into::into))
+    }
+
+    /// Keys of items of type `T`
+    pub fn items<T>(&self) -> &[ItemId] {
+        self.items.get(type_key::<T>()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Names of assets of type `T`
+    pub fn assets<T>(&self) -> &[AssetName] {
+        self.assets.get(type_key::<T>()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Names of expression variables and constants
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+}
+
+/// Strategy picking one of the known names, or occasionally a random name
+/// matching `unknown` regex
+fn known_or_unknown<K: Clone + Debug + From<String> + 'static>(
+    known: &[K],
+    unknown: &'static str,
+) -> BoxedStrategy<K> {
+    let unknown = unknown.prop_map(K::from);
+    if known.is_empty() {
+        return unknown.boxed();
+    }
+    prop_oneof![
+        9 => select(known.to_vec()),
+        1 => unknown,
+    ]
+    .boxed()
+}
+
+/// Strategy for keys of items of type `T`, used for both references and
+/// collection entry keys
+pub fn item_key<T>(pool: &KeyPool) -> BoxedStrategy<ItemId> {
+    known_or_unknown(pool.items::<T>(), "[a-z]{1,4}:[a-z]{1,6}")
+}
+
+/// Strategy for names of assets of type `T`
+pub fn asset_name<T>(pool: &KeyPool) -> BoxedStrategy<AssetName> {
+    known_or_unknown(pool.assets::<T>(), "[a-z_]{1,8}")
+}
+
+/// Strategy for names of expression variables
+fn variable_name(pool: &KeyPool) -> BoxedStrategy<String> {
+    known_or_unknown(pool.variables(), "[a-z_]{1,8}")
+}
+
+macro_rules! arbitrary_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl ArbitrarySerialized for $ty {
+                fn arbitrary_serialized(_pool: &KeyPool) -> BoxedStrategy<Self> {
+                    any::<$ty>().boxed()
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_primitive!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64, bool, String);
+
+impl<T: ArbitrarySerialized + 'static> ArbitrarySerialized for Option<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        weighted(0.75, T::arbitrary_serialized(pool)).boxed()
+    }
+}
+
+impl<T: ArbitrarySerialized + 'static> ArbitrarySerialized for Vec<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        vec(T::arbitrary_serialized(pool), 0..=MAX_COLLECTION_LEN).boxed()
+    }
+}
+
+impl<K, V, S> ArbitrarySerialized for HashMap<K, V, S>
+where
+    K: ArbitrarySerialized + Eq + Hash + 'static,
+    V: ArbitrarySerialized + 'static,
+    S: BuildHasher + Default + 'static,
+{
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        hash_map(
+            K::arbitrary_serialized(pool),
+            V::arbitrary_serialized(pool),
+            0..=MAX_COLLECTION_LEN,
+        )
+        .prop_map(|map| map.into_iter().collect())
+        .boxed()
+    }
+}
+
+impl ArbitrarySerialized for SerializationStringWrapper {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        String::arbitrary_serialized(pool)
+            .prop_map(SerializationStringWrapper)
+            .boxed()
+    }
+}
+
+impl<T: ArbitrarySerialized + 'static> ArbitrarySerialized for SerializationBoxingWrapper<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        T::arbitrary_serialized(pool)
+            .prop_map(SerializationBoxingWrapper)
+            .boxed()
+    }
+}
+
+impl<T: ArbitrarySerialized + 'static> ArbitrarySerialized for ConstOr<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        prop_oneof![
+            3 => T::arbitrary_serialized(pool).prop_map(ConstOr::Value),
+            1 => variable_name(pool).prop_map(ConstOr::Const),
+        ]
+        .boxed()
+    }
+}
+
+impl<T: ArbitrarySerialized + 'static> ArbitrarySerialized for NumericExpr<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        // Expressions are mostly made of variables and operators, so the
+        // parser gets both valid and malformed input
+        let operand = prop_oneof![
+            variable_name(pool),
+            any::<i16>().prop_map(|value| value.to_string()),
+        ];
+        let expr = (operand.clone(), vec(("[-+*/%^ ()]{1,2}", operand), 0..=3)).prop_map(
+            |(first, rest)| {
+                rest.into_iter()
+                    .fold(first, |expr, (op, operand)| format!("{expr}{op}{operand}"))
+            },
+        );
+        prop_oneof![
+            T::arbitrary_serialized(pool).prop_map(NumericExpr::Value),
+            expr.prop_map(NumericExpr::Expr),
+        ]
+        .boxed()
+    }
+}
+
+impl<T: 'static> ArbitrarySerialized for ItemReference<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        item_key::<T>(pool).prop_map(Into::into).boxed()
+    }
+}
+
+impl<T: 'static> ArbitrarySerialized for AssetReference<T> {
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        asset_name::<T>(pool).prop_map(Into::into).boxed()
+    }
+}
+
+impl<DataSerialized: ArbitrarySerialized + 'static, Data: 'static> ArbitrarySerialized
+    for InlineOrIdSerialized<DataSerialized, Data>
+{
+    fn arbitrary_serialized(pool: &KeyPool) -> BoxedStrategy<Self> {
+        prop_oneof![
+            ItemReference::arbitrary_serialized(pool).prop_map(InlineOrIdSerialized::Id),
+            DataSerialized::arbitrary_serialized(pool).prop_map(InlineOrIdSerialized::Inline),
+        ]
+        .boxed()
+    }
+}
+
+/// Strategy for serialized collection entries of items of type `T`
+///
+/// Entry keys are taken from the pool, so generated registries also contain
+/// duplicate items
+pub fn registry_entry<T, DataSerialized: ArbitrarySerialized + 'static>(
+    pool: &KeyPool,
+) -> BoxedStrategy<RegistryEntrySerialized<DataSerialized>> {
+    (
+        item_key::<T>(pool),
+        DataSerialized::arbitrary_serialized(pool),
+    )
+        .prop_map(|(id, data)| RegistryEntrySerialized { id, data })
+        .boxed()
+}
+
+/// Generates `cases` random sets of registry items with references to the
+/// pool keys, and checks that building the registry from them never panics
+/// and never results in an internal error
+///
+/// # Panics
+/// Panics with the minimal failing set of items if the check fails
+pub fn check_registry<Registry>(pool: &KeyPool, cases: u32)
+where
+    Registry: LoadableRegistry,
+    Registry::Item: ArbitrarySerialized + 'static,
+{
+    let mut runner = TestRunner::new(Config {
+        cases,
+        failure_persistence: None,
+        ..Config::default()
+    });
+    let items = vec(
+        Registry::Item::arbitrary_serialized(pool),
+        0..=MAX_REGISTRY_ITEMS,
+    );
+    let result = runner.run(&items, |items| {
+        check_items::<Registry>(items).map_err(|err| TestCaseError::fail(err.to_string()))
+    });
+    if let Err(err) = result {
+        panic!("{err}")
+    }
+}
+
+/// Builds the registry from the items, returning the internal error if one
+/// is produced
+///
+/// Insertion stops at the first error, same as for
+/// [crate::registry::loader::load_directory]
+pub fn check_items<Registry: LoadableRegistry>(
+    items: Vec<Registry::Item>,
+) -> Result<(), DeserializationError<Registry>> {
+    let mut registry = Registry::default();
+    for (i, item) in items.into_iter().enumerate() {
+        if let Err(err) = registry.insert(PathBuf::from(format!("item_{i}.json")), item) {
+            return internal_only(err);
+        }
+    }
+    match registry.into_registry() {
+        Ok(_) => Ok(()),
+        Err(err) => internal_only(err),
+    }
+}
+
+fn internal_only<Registry: LoadableRegistry>(
+    err: DeserializationError<Registry>,
+) -> Result<(), DeserializationError<Registry>> {
+    match err.kind {
+        DeserializationErrorKind::InternalError(_) => Err(err),
+        _ => Ok(()),
+    }
+}
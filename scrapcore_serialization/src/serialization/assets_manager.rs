@@ -1,7 +1,9 @@
+use crate::registry::remap::{DanglingIdError, RegistryRemap, RemapIds};
 use crate::registry::{AssetsHolder, PartialRegistry};
 use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::schema::AssetReference;
 use crate::serialization::{DeserializeModel, SerializationFallback};
-use crate::{AssetName, AssetNameRef};
+use crate::AssetNameRef;
 use assets_manager::Handle;
 
 /// Deserialization for `assets_manager` handler fields
@@ -26,5 +28,12 @@ where
 }
 
 impl<A> SerializationFallback for &'static Handle<A> {
-    type Fallback = AssetName;
+    type Fallback = AssetReference<&'static Handle<A>>;
+}
+
+impl<A> RemapIds for &'static Handle<A> {
+    #[inline(always)]
+    fn remap_ids(&mut self, _remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        Ok(())
+    }
 }
@@ -1,13 +1,16 @@
+use crate::registry::remap::{DanglingIdError, RegistryRemap, RemapIds};
 use crate::registry::{AssetsHolder, PartialRegistry};
 use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::schema::AssetReference;
+use crate::serialization::strong::Strong;
 use crate::serialization::{DeserializeModel, SerializationFallback};
-use crate::{AssetName, AssetNameRef};
+use crate::AssetNameRef;
 use bevy_asset::{Asset, Handle};
 
 /// Deserialization for bevy asset handler fields
 ///
-/// Fields are populated with WEAK handles to the asset
-/// Currently there is no way to request a strong handle
+/// Fields are populated with WEAK handles to the asset, use [Strong] fields
+/// to get strong handles instead
 impl<'a, Registry: PartialRegistry, A: Asset> DeserializeModel<Handle<A>, Registry>
     for AssetNameRef<'a>
 where
@@ -17,15 +20,42 @@ where
         self,
         registry: &mut Registry,
     ) -> Result<Handle<A>, DeserializationError<Registry>> {
-        let name = self.to_ascii_lowercase();
-        if let Some(handle) = registry.get_assets().get(&name) {
-            Ok(handle.0.clone_weak())
-        } else {
-            Err(DeserializationErrorKind::MissingAsset(name, Registry::asset_kind()).into())
-        }
+        find_handle(registry, self).map(Handle::clone_weak)
+    }
+}
+
+/// Deserialization for strong bevy asset handler fields
+impl<'a, Registry: PartialRegistry, A: Asset> DeserializeModel<Strong<Handle<A>>, Registry>
+    for AssetNameRef<'a>
+where
+    Registry: AssetsHolder<Handle<A>>,
+{
+    fn deserialize(
+        self,
+        registry: &mut Registry,
+    ) -> Result<Strong<Handle<A>>, DeserializationError<Registry>> {
+        find_handle(registry, self).map(|handle| Strong(handle.clone()))
+    }
+}
+
+fn find_handle<'r, Registry: PartialRegistry + AssetsHolder<Handle<A>>, A: Asset>(
+    registry: &'r Registry,
+    name: AssetNameRef,
+) -> Result<&'r Handle<A>, DeserializationError<Registry>> {
+    let name = name.to_ascii_lowercase();
+    match registry.get_assets().get(&name) {
+        Some((handle, _)) => Ok(handle),
+        None => Err(DeserializationErrorKind::MissingAsset(name, Registry::asset_kind()).into()),
     }
 }
 
 impl<A: Asset> SerializationFallback for Handle<A> {
-    type Fallback = AssetName;
+    type Fallback = AssetReference<Handle<A>>;
+}
+
+impl<A: Asset> RemapIds for Handle<A> {
+    #[inline(always)]
+    fn remap_ids(&mut self, _remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        Ok(())
+    }
 }
@@ -13,7 +13,7 @@ use std::sync::Arc;
 /// [SerializationFallback] and use this internally
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 #[serde(transparent)]
-pub struct SerializationBoxingWrapper<T>(T);
+pub struct SerializationBoxingWrapper<T>(pub(crate) T);
 
 impl<T: SerializationFallback> SerializationFallback for Arc<T> {
     type Fallback = SerializationBoxingWrapper<T::Fallback>;
@@ -0,0 +1,151 @@
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation, SubschemaValidation};
+use schemars::JsonSchema;
+use serde::de::{Error, IntoDeserializer, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::registry::PartialRegistry;
+use crate::serialization::error::{
+    DeserializationError, DeserializationErrorKind, DeserializationErrorStackItem,
+};
+use crate::serialization::expr::ExprValue;
+use crate::serialization::DeserializeModel;
+
+/// Serialized form of the numeric fields, allowing the value to be either
+/// written directly, or to reference a named constant via `"$name"` syntax
+///
+/// Constants are resolved via [PartialRegistry::get_variable], so they share
+/// storage with expression variables
+///
+/// Default serialized form of the numeric primitive fields of models, and
+/// of their `Option`s, unless the field is marked `#[model(raw)]`.
+/// Deserialization relies on `deserialize_any`, so it only works with
+/// self-describing formats
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ConstOr<T> {
+    Value(T),
+    /// Constant name, without the `$` prefix
+    Const(String),
+}
+
+impl<T: Display> Display for ConstOr<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstOr::Value(value) => write!(f, "{value}"),
+            ConstOr::Const(name) => write!(f, "${name}"),
+        }
+    }
+}
+
+impl<T: Default> Default for ConstOr<T> {
+    fn default() -> Self {
+        ConstOr::Value(T::default())
+    }
+}
+
+impl<T> From<T> for ConstOr<T> {
+    fn from(value: T) -> Self {
+        ConstOr::Value(value)
+    }
+}
+
+impl<T: Serialize> Serialize for ConstOr<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ConstOr::Value(value) => value.serialize(serializer),
+            ConstOr::Const(name) => serializer.collect_str(&format_args!("${name}")),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ConstOr<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ConstOrVisitor(PhantomData))
+    }
+}
+
+struct ConstOrVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for ConstOrVisitor<T> {
+    type Value = ConstOr<T>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a number or a `$constant` reference")
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        T::deserialize(v.into_deserializer()).map(ConstOr::Value)
+    }
+
+    fn visit_i128<E: Error>(self, v: i128) -> Result<Self::Value, E> {
+        T::deserialize(v.into_deserializer()).map(ConstOr::Value)
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        T::deserialize(v.into_deserializer()).map(ConstOr::Value)
+    }
+
+    fn visit_u128<E: Error>(self, v: u128) -> Result<Self::Value, E> {
+        T::deserialize(v.into_deserializer()).map(ConstOr::Value)
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        T::deserialize(v.into_deserializer()).map(ConstOr::Value)
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        match v.strip_prefix('$') {
+            Some(name) => Ok(ConstOr::Const(name.to_string())),
+            None => Err(E::invalid_value(Unexpected::Str(v), &self)),
+        }
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for ConstOr<T> {
+    fn schema_name() -> String {
+        format!("ConstOr_for_{}", T::schema_name())
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let constant = SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^\\$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![gen.subschema_for::<T>(), constant.into()]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl<Registry: PartialRegistry, T: ExprValue> DeserializeModel<T, Registry> for ConstOr<T> {
+    fn deserialize(self, registry: &mut Registry) -> Result<T, DeserializationError<Registry>> {
+        match self {
+            ConstOr::Value(value) => Ok(value),
+            ConstOr::Const(name) => {
+                let value = registry.get_variable(&name).map_err(|e| {
+                    e.context(DeserializationErrorStackItem::ExprVariable(name.clone()))
+                })?;
+                T::from_expr_result(value).ok_or_else(|| {
+                    DeserializationErrorKind::ConstantTypeMismatch {
+                        name,
+                        value,
+                        ty: std::any::type_name::<T>(),
+                    }
+                    .into()
+                })
+            }
+        }
+    }
+}
@@ -54,6 +54,32 @@ pub enum DeserializationErrorKind<Registry: SerializationRegistry> {
     ValueTooLarge { limit: f64, got: f64 },
     #[error("Value is too small, got {} where at least {} is expected.", .got, .limit)]
     ValueTooSmall { limit: f64, got: f64 },
+    #[error("Length is too large, got {} where at most {} is expected.", .got, .limit)]
+    LengthTooLarge { limit: usize, got: usize },
+    #[error("Length is too small, got {} where at least {} is expected.", .got, .limit)]
+    LengthTooSmall { limit: usize, got: usize },
+    #[error("Value must not be empty")]
+    EmptyValue,
+    #[error("Value `{}` doesn't match pattern `{}`", .got, .pattern)]
+    RegexMismatch { pattern: String, got: String },
+    #[error("Value `{}` is not one of the allowed values: {}", .got, .expected.join(", "))]
+    NotOneOf { got: String, expected: Vec<String> },
+    #[error("Value must be finite, got {}", .0)]
+    NonFiniteValue(f64),
+    #[error("Validation failed: {}", .0)]
+    ValidationFailed(String),
+    #[error("Failed to evaluate expression `{}`: {}", .expr, .reason)]
+    BadExpression { expr: String, reason: String },
+    #[error("Expression variable `{}` is missing", .0)]
+    MissingVariable(String),
+    #[error("Expression variable `{}` depends on itself", .0)]
+    RecursiveVariable(String),
+    #[error("Constant `{}` has value {} which is not a valid `{}`", .name, .value, .ty)]
+    ConstantTypeMismatch {
+        name: String,
+        value: f64,
+        ty: &'static str,
+    },
     #[error("Internal error. Please report this to the application author: {}", .0)]
     InternalError(InternalDeserializationError<Registry>),
     #[error("{}", .0)]
@@ -162,12 +188,6 @@ impl<Registry: SerializationRegistry> From<Infallible> for DeserializationError<
     }
 }
 
-// impl From<ExError> for DeserializationError {
-//     fn from(value: ExError) -> Self {
-//         DeserializationErrorKind::BadExpression(value).into()
-//     }
-// }
-
 impl<T, Registry: SerializationRegistry> From<SlabMapDuplicateError<ItemId, T>>
     for DeserializationError<Registry>
 where
@@ -51,38 +51,34 @@ impl<Registry: SerializationRegistry> Display for ItemDiagnosticKind<Registry> {
     }
 }
 
-struct ItemDiagnostic<Registry: SerializationRegistry>(
-    ItemDiagnosticKind<Registry>,
-    Option<Box<ItemDiagnostic<Registry>>>,
-);
-
-impl<Registry: SerializationRegistry + Debug> Debug for ItemDiagnostic<Registry> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("ItemDiagnostic")
-            .field(&self.0)
-            .field(&self.1)
-            .finish()
-    }
-}
+/// Rendered diagnostic, free of registry types so it can be sent across
+/// threads, as required by `miette::Report`
+#[derive(Debug)]
+struct ItemDiagnostic(String, Option<Box<ItemDiagnostic>>);
 
-impl<Registry: SerializationRegistry> Display for ItemDiagnostic<Registry> {
+impl Display for ItemDiagnostic {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
-impl<Registry: SerializationRegistry + Debug> Error for ItemDiagnostic<Registry> {}
+impl Error for ItemDiagnostic {}
 
-impl<Registry: SerializationRegistry + Debug> Diagnostic for ItemDiagnostic<Registry> {
+impl Diagnostic for ItemDiagnostic {
     fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
         self.1.as_ref().map(|e| e.as_ref() as &dyn Diagnostic)
     }
 }
 
 impl<Registry: SerializationRegistry> DeserializationError<Registry> {
-    pub fn diagnostic(self) -> impl Diagnostic {
+    pub fn diagnostic(self) -> impl Diagnostic + Send + Sync + 'static {
         self.stack.into_iter().fold(
-            ItemDiagnostic(ItemDiagnosticKind::Cause(self.kind), None),
-            |err, item| ItemDiagnostic(ItemDiagnosticKind::Path(item), Some(Box::new(err))),
+            ItemDiagnostic(ItemDiagnosticKind::Cause(self.kind).to_string(), None),
+            |err, item| {
+                ItemDiagnostic(
+                    ItemDiagnosticKind::Path(item).to_string(),
+                    Some(Box::new(err)),
+                )
+            },
         )
     }
 }
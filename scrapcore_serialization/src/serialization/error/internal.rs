@@ -43,6 +43,8 @@ pub enum InternalDeserializationError<Registry: SerializationRegistry> {
     ConversionMissingSingleton(Registry::ItemKind),
     #[error("Singleton {}({}) is not processed, was `process_raw_singleton` not invoked?", .1, .0)]
     ConversionUnprocessedSingleton(PathIdentifier, Registry::ItemKind),
+    #[error("Validation pattern `{}` is not a valid regex: {}", .pattern, .reason)]
+    BadRegex { pattern: String, reason: String },
     #[error("Item {}({}) is not present after hot reload, full mod reload is required.", .1, .0)]
     UnfilledHotReloadingSlot(ItemId, Registry::ItemKind),
 }
@@ -0,0 +1,276 @@
+use crate::registry::PartialRegistry;
+use crate::serialization::error::{
+    DeserializationError, DeserializationErrorKind, DeserializationErrorStackItem,
+};
+use crate::serialization::DeserializeModel;
+use duplicate::duplicate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Numeric value that can be written either as a plain number, or as an
+/// arithmetic expression string, like `"base_seats * 2 + 10"`
+///
+/// Expressions are evaluated at deserialization time, with variables being
+/// resolved via [PartialRegistry::get_variable]
+///
+/// Supported operators are `+`, `-`, `*`, `/`, `%`, `^` (power), unary minus,
+/// and parentheses. Variables can optionally be prefixed with `$`, same as
+/// constant references in [crate::serialization::constant::ConstOr]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum NumericExpr<T> {
+    Value(T),
+    Expr(String),
+}
+
+impl<T> From<T> for NumericExpr<T> {
+    fn from(value: T) -> Self {
+        NumericExpr::Value(value)
+    }
+}
+
+/// Numeric types that can be produced by evaluating an expression
+pub trait ExprValue: Sized {
+    /// Converts the expression result into the target type, returning `None`
+    /// if the value can not be represented
+    fn from_expr_result(value: f64) -> Option<Self>;
+
+    /// Converts the value into the type used for expression evaluation
+    fn into_expr_value(self) -> f64;
+}
+
+duplicate! {
+    [
+        ty;
+        [ i8 ]; [ i16 ]; [ i32 ]; [ i64 ]; [ i128 ];
+        [ u8 ]; [ u16 ]; [ u32 ]; [ u64 ]; [ u128 ];
+    ]
+    impl ExprValue for ty {
+        #[inline]
+        fn from_expr_result(value: f64) -> Option<Self> {
+            // `ty::MAX + 1` is a power of two, so it's exact as `f64`, unlike
+            // `ty::MAX` of 64 and 128 bit types, which rounds up to it
+            let upper = ty::MAX as f64 + 1.0;
+            (value.fract() == 0.0 && value >= ty::MIN as f64 && value < upper)
+                .then_some(value as ty)
+        }
+
+        #[inline(always)]
+        fn into_expr_value(self) -> f64 {
+            self as f64
+        }
+    }
+}
+
+duplicate! {
+    [
+        ty;
+        [ f32 ]; [ f64 ];
+    ]
+    impl ExprValue for ty {
+        #[inline(always)]
+        #[allow(clippy::unnecessary_cast)]
+        fn from_expr_result(value: f64) -> Option<Self> {
+            Some(value as ty)
+        }
+
+        #[inline(always)]
+        #[allow(clippy::unnecessary_cast)]
+        fn into_expr_value(self) -> f64 {
+            self as f64
+        }
+    }
+}
+
+impl<Registry: PartialRegistry, T: ExprValue> DeserializeModel<T, Registry> for NumericExpr<T> {
+    fn deserialize(self, registry: &mut Registry) -> Result<T, DeserializationError<Registry>> {
+        match self {
+            NumericExpr::Value(value) => Ok(value),
+            NumericExpr::Expr(expr) => {
+                let value = eval_expr(&expr, |name| {
+                    registry.get_variable(name).map_err(|e| {
+                        e.context(DeserializationErrorStackItem::ExprVariable(
+                            name.to_string(),
+                        ))
+                    })
+                })?;
+                T::from_expr_result(value).ok_or_else(|| {
+                    DeserializationErrorKind::BadExpression {
+                        reason: format!(
+                            "result {} is not a valid `{}` value",
+                            value,
+                            std::any::type_name::<T>()
+                        ),
+                        expr,
+                    }
+                    .into()
+                })
+            }
+        }
+    }
+}
+
+/// Evaluates the expression, using `resolve` to look up variable values
+///
+/// Syntax errors are reported as [DeserializationErrorKind::BadExpression],
+/// while variable errors are passed through as-is
+pub fn eval_expr<Registry: PartialRegistry>(
+    expr: &str,
+    resolve: impl FnMut(&str) -> Result<f64, DeserializationError<Registry>>,
+) -> Result<f64, DeserializationError<Registry>> {
+    let mut parser = ExprParser {
+        src: expr,
+        pos: 0,
+        resolve,
+    };
+    let value = parser.expr().and_then(|value| {
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(value),
+            Some(c) => Err(parser.syntax_error(format!("unexpected `{c}`"))),
+        }
+    });
+
+    value.map_err(|err| match err {
+        ExprError::Syntax(reason) => DeserializationErrorKind::BadExpression {
+            expr: expr.to_string(),
+            reason,
+        }
+        .into(),
+        ExprError::Variable(err) => err,
+    })
+}
+
+enum ExprError<Registry: PartialRegistry> {
+    Syntax(String),
+    Variable(DeserializationError<Registry>),
+}
+
+/// Recursive descent parser, evaluating the expression as it goes
+struct ExprParser<'a, F> {
+    src: &'a str,
+    pos: usize,
+    resolve: F,
+}
+
+impl<'a, Registry: PartialRegistry, F> ExprParser<'a, F>
+where
+    F: FnMut(&str) -> Result<f64, DeserializationError<Registry>>,
+{
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes the next non-whitespace character if it is one of `ops`
+    fn take_op(&mut self, ops: &[char]) -> Option<char> {
+        self.skip_whitespace();
+        let c = self.peek().filter(|c| ops.contains(c))?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn syntax_error(&self, reason: impl Into<String>) -> ExprError<Registry> {
+        ExprError::Syntax(format!("{} at position {}", reason.into(), self.pos))
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<f64, ExprError<Registry>> {
+        let mut value = self.term()?;
+        while let Some(op) = self.take_op(&['+', '-']) {
+            let rhs = self.term()?;
+            match op {
+                '+' => value += rhs,
+                _ => value -= rhs,
+            }
+        }
+        Ok(value)
+    }
+
+    /// term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, ExprError<Registry>> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.take_op(&['*', '/', '%']) {
+            let rhs = self.unary()?;
+            match op {
+                '*' => value *= rhs,
+                '/' => value /= rhs,
+                _ => value %= rhs,
+            }
+        }
+        Ok(value)
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, ExprError<Registry>> {
+        match self.take_op(&['-', '+']) {
+            Some('-') => Ok(-self.unary()?),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    /// power := atom ('^' unary)?
+    fn power(&mut self) -> Result<f64, ExprError<Registry>> {
+        let base = self.atom()?;
+        if self.take_op(&['^']).is_some() {
+            let exp = self.unary()?;
+            return Ok(base.powf(exp));
+        }
+        Ok(base)
+    }
+
+    /// atom := number | '$'? variable | '(' expr ')'
+    fn atom(&mut self) -> Result<f64, ExprError<Registry>> {
+        self.skip_whitespace();
+        let Some(c) = self.peek() else {
+            return Err(self.syntax_error("unexpected end of expression"));
+        };
+
+        if c == '(' {
+            self.pos += 1;
+            let value = self.expr()?;
+            if self.take_op(&[')']).is_none() {
+                return Err(self.syntax_error("expected `)`"));
+            }
+            Ok(value)
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = self.pos;
+            let mut prev = c;
+            for c in self.src[start..].chars() {
+                let exponent_sign = (c == '-' || c == '+') && (prev == 'e' || prev == 'E');
+                if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                    break;
+                }
+                self.pos += c.len_utf8();
+                prev = c;
+            }
+            let number = &self.src[start..self.pos];
+            number
+                .parse::<f64>()
+                .map_err(|_| self.syntax_error(format!("invalid number `{number}`")))
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            if c == '$' {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            for c in self.src[start..].chars() {
+                if !(c.is_alphanumeric() || c == '_' || c == ':' || c == '.') {
+                    break;
+                }
+                self.pos += c.len_utf8();
+            }
+            let name = &self.src[start..self.pos];
+            if name.is_empty() {
+                return Err(self.syntax_error("expected variable name"));
+            }
+            (self.resolve)(name).map_err(ExprError::Variable)
+        } else {
+            Err(self.syntax_error(format!("unexpected `{c}`")))
+        }
+    }
+}
@@ -17,3 +17,16 @@ pub trait DeserializeFrom<Registry: PartialRegistry>: Sized {
 }
 
 impl<Registry: PartialRegistry, T> DeserializeFrom<Registry> for T {}
+
+/// Deserializes a present field into `Some`, even if its value is `null`
+///
+/// Used with `#[serde(default)]` for optional serialized fields, so an
+/// explicit `null` of the `Option` field can be told apart from a missing
+/// field
+pub fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
@@ -1,13 +1,16 @@
+use crate::registry::remap::{DanglingIdError, RegistryRemap, RemapIds};
 use crate::registry::{AssetsHolder, PartialRegistry};
 use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::schema::AssetReference;
+use crate::serialization::strong::Strong;
 use crate::serialization::{DeserializeModel, SerializationFallback};
-use crate::{AssetName, AssetNameRef};
+use crate::AssetNameRef;
 use macroquad::texture::Texture2D;
 
 /// Deserialization for Macroquad Texture2D
 ///
-/// Fields are populated with WEAK handles to the asset
-/// Currently there is no way to request a strong handle
+/// Fields are populated with WEAK handles to the asset, use [Strong] fields
+/// to get strong handles instead
 impl<'a, Registry: PartialRegistry> DeserializeModel<Texture2D, Registry> for AssetNameRef<'a>
 where
     Registry: AssetsHolder<Texture2D>,
@@ -16,15 +19,42 @@ where
         self,
         registry: &mut Registry,
     ) -> Result<Texture2D, DeserializationError<Registry>> {
-        let name = self.to_ascii_lowercase();
-        if let Some(handle) = registry.get_assets().get(&name) {
-            Ok(handle.0.weak_clone())
-        } else {
-            Err(DeserializationErrorKind::MissingAsset(name, Registry::asset_kind()).into())
-        }
+        find_texture(registry, self).map(Texture2D::weak_clone)
+    }
+}
+
+/// Deserialization for strong Macroquad Texture2D fields
+impl<'a, Registry: PartialRegistry> DeserializeModel<Strong<Texture2D>, Registry>
+    for AssetNameRef<'a>
+where
+    Registry: AssetsHolder<Texture2D>,
+{
+    fn deserialize(
+        self,
+        registry: &mut Registry,
+    ) -> Result<Strong<Texture2D>, DeserializationError<Registry>> {
+        find_texture(registry, self).map(|texture| Strong(texture.clone()))
+    }
+}
+
+fn find_texture<'r, Registry: PartialRegistry + AssetsHolder<Texture2D>>(
+    registry: &'r Registry,
+    name: AssetNameRef,
+) -> Result<&'r Texture2D, DeserializationError<Registry>> {
+    let name = name.to_ascii_lowercase();
+    match registry.get_assets().get(&name) {
+        Some((texture, _)) => Ok(texture),
+        None => Err(DeserializationErrorKind::MissingAsset(name, Registry::asset_kind()).into()),
     }
 }
 
 impl SerializationFallback for Texture2D {
-    type Fallback = AssetName;
+    type Fallback = AssetReference<Texture2D>;
+}
+
+impl RemapIds for Texture2D {
+    #[inline(always)]
+    fn remap_ids(&mut self, _remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        Ok(())
+    }
 }
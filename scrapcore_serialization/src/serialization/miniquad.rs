@@ -1,7 +1,9 @@
+use crate::registry::remap::{DanglingIdError, RegistryRemap, RemapIds};
 use crate::registry::{AssetsHolder, PartialRegistry};
 use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+use crate::serialization::schema::AssetReference;
 use crate::serialization::{DeserializeModel, SerializationFallback};
-use crate::{AssetName, AssetNameRef};
+use crate::AssetNameRef;
 use miniquad::TextureId;
 
 /// Deserialization for miniquad TextureId
@@ -23,5 +25,12 @@ where
 }
 
 impl SerializationFallback for TextureId {
-    type Fallback = AssetName;
+    type Fallback = AssetReference<TextureId>;
+}
+
+impl RemapIds for TextureId {
+    #[inline(always)]
+    fn remap_ids(&mut self, _remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        Ok(())
+    }
 }
@@ -16,7 +16,16 @@ duplicate! {
             Ok(self)
         }
     }
+}
 
+duplicate! {
+    [
+        ty;
+        [ i8 ]; [ i16 ]; [ i32 ]; [ i64 ]; [ i128 ];
+        [ u8 ]; [ u16 ]; [ u32 ]; [ u64 ]; [ u128 ];
+        [ f32 ]; [ f64 ];
+        [ bool ];
+    ]
     impl SerializationFallback for ty {
         type Fallback = ty;
     }
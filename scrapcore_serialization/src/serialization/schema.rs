@@ -0,0 +1,367 @@
+//! JSON schema support for references to registry items and assets
+//!
+//! Id and asset fields are serialized as [ItemReference] and
+//! [AssetReference], which are plain strings in the data, but get a separate
+//! schema definition for every referenced kind. [SchemaReferences] collects
+//! item keys and asset names known to a registry, names the references by
+//! the registry kinds, and restricts them to the known values
+use std::any::type_name;
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use ahash::AHashMap;
+use duplicate::duplicate_item;
+use itertools::Itertools;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject};
+use schemars::visit::{visit_schema_object, Visitor};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::registry::{CollectionItemId, PartialRegistry};
+use crate::serialization::error::DeserializationError;
+use crate::serialization::DeserializeModel;
+use crate::{AssetName, ItemId};
+
+/// Schema extension holding the registry kind name of the referenced item
+pub const REF_EXTENSION: &str = "x-scrapcore-ref";
+/// Schema extension holding the registry kind name of the referenced asset
+pub const ASSET_EXTENSION: &str = "x-scrapcore-asset";
+
+/// Registry-independent key of the type, linking references to the registry
+/// kinds holding the type
+///
+/// Wrapper types are stripped, so both `game::Person` and
+/// `RegistryEntry<game::Person>` are keyed as `game::Person`, and asset
+/// handles like `Handle<Image>` are keyed by the asset type. Module path is
+/// kept, so same-named types from different modules get different keys.
+///
+/// Schemas of the references hold this key until [SchemaReferences::apply]
+/// replaces it with the registry kind name
+pub fn type_key<T: ?Sized>() -> &'static str {
+    let mut name = type_name::<T>();
+    while let (Some(start), Some(end)) = (name.find('<'), name.rfind('>')) {
+        name = &name[start + 1..end];
+    }
+    name
+}
+
+/// Type key without the module path, used for schema definition names
+fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = type_key::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[duplicate_item(
+    module      reference        raw         extension         suffix     kind_doc;
+    [item_ref]  [ItemReference]  [ItemId]    [REF_EXTENSION]   ["Id"]     ["Serialized reference to an item of type `T`, written as the item key"];
+    [asset_ref] [AssetReference] [AssetName] [ASSET_EXTENSION] ["Asset"]  ["Serialized reference to an asset of type `T`, written as the asset name"];
+)]
+mod module {
+    use super::*;
+
+    #[doc = kind_doc]
+    pub struct reference<T> {
+        name: raw,
+        _marker: PhantomData<fn() -> T>,
+    }
+
+    impl<T> reference<T> {
+        pub fn as_str(&self) -> &str {
+            &self.name
+        }
+
+        pub fn into_inner(self) -> raw {
+            self.name
+        }
+    }
+
+    impl<T> From<raw> for reference<T> {
+        fn from(name: raw) -> Self {
+            Self {
+                name,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    impl<T> From<&str> for reference<T> {
+        fn from(name: &str) -> Self {
+            raw::from(name).into()
+        }
+    }
+
+    impl<T> AsRef<str> for reference<T> {
+        fn as_ref(&self) -> &str {
+            &self.name
+        }
+    }
+
+    impl<T> Clone for reference<T> {
+        #[allow(clippy::clone_on_copy)]
+        fn clone(&self) -> Self {
+            self.name.clone().into()
+        }
+    }
+
+    impl<T> Debug for reference<T> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            Debug::fmt(&self.name, f)
+        }
+    }
+
+    impl<T> Display for reference<T> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            Display::fmt(&self.name, f)
+        }
+    }
+
+    impl<T> PartialEq for reference<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.name == other.name
+        }
+    }
+
+    impl<T> Eq for reference<T> {}
+
+    impl<T> Hash for reference<T> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.name.hash(state)
+        }
+    }
+
+    impl<T> Serialize for reference<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.name.serialize(serializer)
+        }
+    }
+
+    impl<'de, T> Deserialize<'de> for reference<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            <raw as Deserialize>::deserialize(deserializer).map(Into::into)
+        }
+    }
+
+    impl<T> JsonSchema for reference<T> {
+        fn schema_name() -> String {
+            format!("{}{}", short_type_name::<T>(), suffix)
+        }
+
+        fn schema_id() -> Cow<'static, str> {
+            Cow::Owned(format!("{}<{}>", stringify!(reference), type_name::<T>()))
+        }
+
+        fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+            let mut schema = SchemaObject {
+                instance_type: Some(InstanceType::String.into()),
+                ..Default::default()
+            };
+            schema
+                .extensions
+                .insert(extension.to_string(), type_key::<T>().into());
+            schema.into()
+        }
+    }
+}
+
+pub use asset_ref::AssetReference;
+pub use item_ref::ItemReference;
+
+impl<Registry: PartialRegistry, Data> DeserializeModel<CollectionItemId<Data>, Registry>
+    for ItemReference<Data>
+where
+    for<'a> &'a str: DeserializeModel<CollectionItemId<Data>, Registry>,
+{
+    fn deserialize(
+        self,
+        registry: &mut Registry,
+    ) -> Result<CollectionItemId<Data>, DeserializationError<Registry>> {
+        self.as_str().deserialize(registry)
+    }
+}
+
+impl<Registry: PartialRegistry, Asset> DeserializeModel<Asset, Registry> for AssetReference<Asset>
+where
+    for<'a> &'a str: DeserializeModel<Asset, Registry>,
+{
+    fn deserialize(self, registry: &mut Registry) -> Result<Asset, DeserializationError<Registry>> {
+        self.as_str().deserialize(registry)
+    }
+}
+
+/// Schema of the serialized field `S`, annotated as a reference to items of
+/// type `T`
+///
+/// Used by `#[model(id)]` and `#[model(id_of)]` fields, for the reference to
+/// be visible on the field itself even when its serialized type is a plain
+/// string. Optional fields are wrapped as `Option<ReferenceSchema<S, T>>`,
+/// with `S` being the inner serialized type
+pub struct ReferenceSchema<S, T>(PhantomData<(S, fn() -> T)>);
+
+impl<S: JsonSchema, T> JsonSchema for ReferenceSchema<S, T> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        S::schema_name()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        Cow::Owned(format!(
+            "ReferenceSchema<{}, {}>",
+            S::schema_id(),
+            type_name::<T>()
+        ))
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = gen.subschema_for::<S>().into_object();
+        schema
+            .extensions
+            .insert(REF_EXTENSION.to_string(), type_key::<T>().into());
+        schema.into()
+    }
+}
+
+/// Schema default of `#[model(default)]` fields, produced from the `Default`
+/// impl of the serialized type `T`
+///
+/// Dispatch relies on auto-ref, so calling
+/// `(&DefaultProbe::<T>::new()).schema_default()` picks [ProbeDefault] when
+/// `T` implements `Default`, and falls back to [ProbeNoDefault], leaving the
+/// schema without a default, otherwise
+#[doc(hidden)]
+pub struct DefaultProbe<T>(PhantomData<fn() -> T>);
+
+impl<T> DefaultProbe<T> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ProbeDefault<T> {
+    fn schema_default(&self) -> Option<T>;
+}
+
+impl<T: Default> ProbeDefault<T> for DefaultProbe<T> {
+    fn schema_default(&self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait ProbeNoDefault<T> {
+    fn schema_default(&self) -> Option<T>;
+}
+
+impl<T> ProbeNoDefault<T> for &DefaultProbe<T> {
+    fn schema_default(&self) -> Option<T> {
+        None
+    }
+}
+
+/// Item keys and asset names known to a registry, grouped by the registry
+/// kinds
+///
+/// Registries generated by `#[registry]` macro provide `schema_references`
+/// method for collecting those
+#[derive(Debug, Clone, Default)]
+pub struct SchemaReferences {
+    /// Kind name and item keys, by the type key of the items
+    items: AHashMap<&'static str, (&'static str, Vec<ItemId>)>,
+    /// Kind name and asset names, by the type key of the assets
+    assets: AHashMap<&'static str, (&'static str, Vec<AssetName>)>,
+}
+
+impl SchemaReferences {
+    /// Adds keys of items of type `T`, stored under the registry kind `kind`
+    pub fn add_items<T>(
+        &mut self,
+        kind: &'static str,
+        keys: impl IntoIterator<Item = impl Into<ItemId>>,
+    ) {
+        let (_, known) = self.items.entry(type_key::<T>()).or_insert((kind, vec![]));
+        known.extend(keys.into_iter().map(Into::into))
+    }
+
+    /// Adds names of assets of type `T`, stored under the registry kind
+    /// `kind`
+    pub fn add_assets<T>(
+        &mut self,
+        kind: &'static str,
+        names: impl IntoIterator<Item = impl Into<AssetName>>,
+    ) {
+        let (_, known) = self.assets.entry(type_key::<T>()).or_insert((kind, vec![]));
+        known.extend(names.into_iter().map(Into::into))
+    }
+
+    /// Names item and asset references in the schema by their registry
+    /// kinds, and restricts them to the known keys
+    ///
+    /// References of kinds without any known keys are left as free strings,
+    /// and references to types outside of the registry lose their
+    /// annotation
+    pub fn apply(&self, schema: &mut RootSchema) {
+        EnrichVisitor(self).visit_root_schema(schema)
+    }
+
+    /// Generates schema for `T`, with item and asset references named by
+    /// the registry kinds and restricted to the known keys
+    pub fn schema_for<T: JsonSchema>(&self) -> RootSchema {
+        let mut schema = schemars::schema_for!(T);
+        self.apply(&mut schema);
+        schema
+    }
+}
+
+struct EnrichVisitor<'a>(&'a SchemaReferences);
+
+impl Visitor for EnrichVisitor<'_> {
+    fn visit_schema_object(&mut self, schema: &mut SchemaObject) {
+        let items = resolve_kind(schema, REF_EXTENSION, &self.0.items).map(enum_values);
+        let assets = resolve_kind(schema, ASSET_EXTENSION, &self.0.assets).map(enum_values);
+        // Without any known keys the `enum` would reject every value
+        if let Some(known) = items.or(assets).filter(|known| !known.is_empty()) {
+            schema.enum_values = Some(known);
+        }
+
+        visit_schema_object(self, schema)
+    }
+}
+
+/// Replaces the type key in the reference extension with the registry kind
+/// name, returning the values known for the kind
+fn resolve_kind<'a, V>(
+    schema: &mut SchemaObject,
+    extension: &str,
+    kinds: &'a AHashMap<&'static str, (&'static str, Vec<V>)>,
+) -> Option<&'a [V]> {
+    let key = schema.extensions.get(extension)?.as_str()?;
+    if let Some((kind, known)) = kinds.get(key) {
+        schema
+            .extensions
+            .insert(extension.to_string(), (*kind).into());
+        return Some(known);
+    }
+    // Schemas that were already applied hold the kind names
+    if !kinds.values().any(|(kind, _)| *kind == key) {
+        schema.extensions.remove(extension);
+    }
+    None
+}
+
+/// Sorted and deduplicated values for the schema `enum`
+fn enum_values<V: for<'a> From<&'a str>>(known: &[impl AsRef<str>]) -> Vec<V> {
+    known
+        .iter()
+        .map(AsRef::as_ref)
+        .sorted()
+        .dedup()
+        .map(Into::into)
+        .collect()
+}
@@ -0,0 +1,66 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::registry::remap::{DanglingIdError, RegistryRemap, RemapIds};
+use crate::registry::PartialRegistry;
+use crate::serialization::error::DeserializationError;
+use crate::serialization::schema::AssetReference;
+use crate::serialization::{DeserializeModel, SerializationFallback};
+
+/// Asset field holding a STRONG handle to the asset
+///
+/// Asset fields like bevy `Handle<A>` or macroquad `Texture2D` are populated
+/// with weak handles, which stop resolving once the registry assets are
+/// dropped. Fields of type `Strong<Handle<A>>` keep the asset alive for as
+/// long as the item exists, and are serialized the same way as the wrapped
+/// type
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Strong<T>(pub T);
+
+impl<T> Strong<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Strong<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Strong<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> AsRef<T> for Strong<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: SerializationFallback> SerializationFallback for Strong<T> {
+    type Fallback = T::Fallback;
+}
+
+impl<Registry: PartialRegistry, T> DeserializeModel<Strong<T>, Registry> for AssetReference<T>
+where
+    for<'a> &'a str: DeserializeModel<Strong<T>, Registry>,
+{
+    fn deserialize(
+        self,
+        registry: &mut Registry,
+    ) -> Result<Strong<T>, DeserializationError<Registry>> {
+        self.as_str().deserialize(registry)
+    }
+}
+
+impl<T: RemapIds> RemapIds for Strong<T> {
+    #[inline(always)]
+    fn remap_ids(&mut self, remap: &RegistryRemap) -> Result<(), DanglingIdError> {
+        self.0.remap_ids(remap)
+    }
+}
@@ -0,0 +1,260 @@
+//! Validators applied by `#[model(...)]` attributes, on top of
+//! [crate::serialization::min_max]
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+
+use duplicate::duplicate_item;
+
+use crate::registry::SerializationRegistry;
+use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+
+#[cfg(feature = "regex")]
+pub use regex_validator::{ApplyRegex, LazyRegex};
+
+/// Types with a length that can be checked by `len_min`, `len_max` and
+/// `non_empty` validators
+///
+/// Strings are measured in characters, collections in items
+pub trait ValidatedLength {
+    fn validated_len(&self) -> usize;
+}
+
+impl ValidatedLength for String {
+    fn validated_len(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl<T> ValidatedLength for Vec<T> {
+    fn validated_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V, S> ValidatedLength for HashMap<K, V, S> {
+    fn validated_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T, S> ValidatedLength for HashSet<T, S> {
+    fn validated_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V> ValidatedLength for BTreeMap<K, V> {
+    fn validated_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> ValidatedLength for BTreeSet<T> {
+    fn validated_len(&self) -> usize {
+        self.len()
+    }
+}
+
+pub trait ApplyLenMin<Registry: SerializationRegistry>: Sized {
+    fn apply(self, min: usize) -> Result<Self, DeserializationError<Registry>>;
+}
+
+pub trait ApplyLenMax<Registry: SerializationRegistry>: Sized {
+    fn apply(self, max: usize) -> Result<Self, DeserializationError<Registry>>;
+}
+
+pub trait ApplyNonEmpty<Registry: SerializationRegistry>: Sized {
+    fn apply(self) -> Result<Self, DeserializationError<Registry>>;
+}
+
+impl<Registry: SerializationRegistry, T: ValidatedLength> ApplyLenMin<Registry> for T {
+    fn apply(self, min: usize) -> Result<Self, DeserializationError<Registry>> {
+        let got = self.validated_len();
+        if got < min {
+            return Err(DeserializationErrorKind::LengthTooSmall { limit: min, got }.into());
+        }
+        Ok(self)
+    }
+}
+
+impl<Registry: SerializationRegistry, T: ValidatedLength> ApplyLenMax<Registry> for T {
+    fn apply(self, max: usize) -> Result<Self, DeserializationError<Registry>> {
+        let got = self.validated_len();
+        if got > max {
+            return Err(DeserializationErrorKind::LengthTooLarge { limit: max, got }.into());
+        }
+        Ok(self)
+    }
+}
+
+impl<Registry: SerializationRegistry, T: ValidatedLength> ApplyNonEmpty<Registry> for T {
+    fn apply(self) -> Result<Self, DeserializationError<Registry>> {
+        if self.validated_len() == 0 {
+            return Err(DeserializationErrorKind::EmptyValue.into());
+        }
+        Ok(self)
+    }
+}
+
+impl<Registry: SerializationRegistry, T: ApplyLenMin<Registry>> ApplyLenMin<Registry>
+    for Option<T>
+{
+    fn apply(self, min: usize) -> Result<Self, DeserializationError<Registry>> {
+        self.map(|e| e.apply(min)).transpose()
+    }
+}
+
+impl<Registry: SerializationRegistry, T: ApplyLenMax<Registry>> ApplyLenMax<Registry>
+    for Option<T>
+{
+    fn apply(self, max: usize) -> Result<Self, DeserializationError<Registry>> {
+        self.map(|e| e.apply(max)).transpose()
+    }
+}
+
+impl<Registry: SerializationRegistry, T: ApplyNonEmpty<Registry>> ApplyNonEmpty<Registry>
+    for Option<T>
+{
+    fn apply(self) -> Result<Self, DeserializationError<Registry>> {
+        self.map(|e| e.apply()).transpose()
+    }
+}
+
+pub trait ApplyFinite<Registry: SerializationRegistry>: Sized {
+    fn apply(self) -> Result<Self, DeserializationError<Registry>>;
+}
+
+#[duplicate_item(ty; [f32]; [f64];)]
+impl<Registry: SerializationRegistry> ApplyFinite<Registry> for ty {
+    fn apply(self) -> Result<Self, DeserializationError<Registry>> {
+        if !self.is_finite() {
+            #[allow(clippy::unnecessary_cast)]
+            return Err(DeserializationErrorKind::NonFiniteValue(self as f64).into());
+        }
+        Ok(self)
+    }
+}
+
+impl<Registry: SerializationRegistry, T: ApplyFinite<Registry>> ApplyFinite<Registry>
+    for Option<T>
+{
+    fn apply(self) -> Result<Self, DeserializationError<Registry>> {
+        self.map(|e| e.apply()).transpose()
+    }
+}
+
+pub trait ApplyOneOf<Registry: SerializationRegistry, Item>: Sized {
+    fn apply(self, options: &[Item]) -> Result<Self, DeserializationError<Registry>>;
+}
+
+fn not_one_of<Registry: SerializationRegistry>(
+    got: impl Display,
+    options: &[impl Display],
+) -> DeserializationError<Registry> {
+    DeserializationErrorKind::NotOneOf {
+        got: got.to_string(),
+        expected: options.iter().map(|e| e.to_string()).collect(),
+    }
+    .into()
+}
+
+#[duplicate_item(
+    ty;
+    [ i8 ]; [ i16 ]; [ i32 ]; [ i64 ]; [ i128 ];
+    [ u8 ]; [ u16 ]; [ u32 ]; [ u64 ]; [ u128 ];
+    [ f32 ]; [ f64 ];
+    [ bool ]; [ char ];
+)]
+impl<Registry: SerializationRegistry> ApplyOneOf<Registry, ty> for ty {
+    fn apply(self, options: &[ty]) -> Result<Self, DeserializationError<Registry>> {
+        if !options.contains(&self) {
+            return Err(not_one_of(self, options));
+        }
+        Ok(self)
+    }
+}
+
+impl<'a, Registry: SerializationRegistry> ApplyOneOf<Registry, &'a str> for String {
+    fn apply(self, options: &[&'a str]) -> Result<Self, DeserializationError<Registry>> {
+        if !options.contains(&self.as_str()) {
+            return Err(not_one_of(self, options));
+        }
+        Ok(self)
+    }
+}
+
+impl<Registry: SerializationRegistry, Item, T: ApplyOneOf<Registry, Item>>
+    ApplyOneOf<Registry, Item> for Option<T>
+{
+    fn apply(self, options: &[Item]) -> Result<Self, DeserializationError<Registry>> {
+        self.map(|e| e.apply(options)).transpose()
+    }
+}
+
+#[cfg(feature = "regex")]
+mod regex_validator {
+    use std::sync::OnceLock;
+
+    use regex::Regex;
+
+    use crate::registry::SerializationRegistry;
+    use crate::serialization::error::internal::InternalDeserializationError;
+    use crate::serialization::error::{DeserializationError, DeserializationErrorKind};
+
+    /// Regex that is compiled on the first use
+    ///
+    /// Used by the `#[model(regex = "...")]` validator to only compile each
+    /// pattern once
+    #[derive(Debug)]
+    pub struct LazyRegex {
+        pattern: &'static str,
+        regex: OnceLock<Result<Regex, regex::Error>>,
+    }
+
+    impl LazyRegex {
+        pub const fn new(pattern: &'static str) -> Self {
+            Self {
+                pattern,
+                regex: OnceLock::new(),
+            }
+        }
+
+        pub fn get<Registry: SerializationRegistry>(
+            &self,
+        ) -> Result<&Regex, DeserializationError<Registry>> {
+            self.regex
+                .get_or_init(|| Regex::new(self.pattern))
+                .as_ref()
+                .map_err(|err| {
+                    InternalDeserializationError::BadRegex {
+                        pattern: self.pattern.to_string(),
+                        reason: err.to_string(),
+                    }
+                    .into()
+                })
+        }
+    }
+
+    pub trait ApplyRegex<Registry: SerializationRegistry>: Sized {
+        fn apply(self, regex: &LazyRegex) -> Result<Self, DeserializationError<Registry>>;
+    }
+
+    impl<Registry: SerializationRegistry> ApplyRegex<Registry> for String {
+        fn apply(self, regex: &LazyRegex) -> Result<Self, DeserializationError<Registry>> {
+            if !regex.get()?.is_match(&self) {
+                return Err(DeserializationErrorKind::RegexMismatch {
+                    pattern: regex.pattern.to_string(),
+                    got: self,
+                }
+                .into());
+            }
+            Ok(self)
+        }
+    }
+
+    impl<Registry: SerializationRegistry, T: ApplyRegex<Registry>> ApplyRegex<Registry> for Option<T> {
+        fn apply(self, regex: &LazyRegex) -> Result<Self, DeserializationError<Registry>> {
+            self.map(|e| e.apply(regex)).transpose()
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Deserialization helpers of the versioned serialized models, generated by
+//! the `#[model(version = N)]` attribute
+//!
+//! The data is buffered first, so the `"version"` field can be inspected
+//! before picking the serialized shape. Versions can be written either as
+//! strings (`"version": "1"`) or as integers (`"version": 1`), and data
+//! lacking the field is treated as the latest version
+use serde::de::{DeserializeOwned, Error};
+use serde::Deserializer;
+pub use serde_json::Value;
+
+/// Name of the version field
+pub const VERSION_FIELD: &str = "version";
+
+/// Buffers the data, taking out its version field
+///
+/// Data that is not an object is returned as-is, without a version
+pub fn split_version<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<(Option<u32>, Value), D::Error> {
+    let mut data = <Value as serde::Deserialize>::deserialize(deserializer)?;
+    let Some(version) = data
+        .as_object_mut()
+        .and_then(|obj| obj.remove(VERSION_FIELD))
+    else {
+        return Ok((None, data));
+    };
+    let parsed = match &version {
+        Value::String(version) => version.parse().ok(),
+        Value::Number(version) => version.as_u64().and_then(|v| u32::try_from(v).ok()),
+        _ => None,
+    };
+    match parsed {
+        Some(version) => Ok((Some(version), data)),
+        None => Err(D::Error::custom(format_args!(
+            "invalid model version `{version}`, expected a positive integer or a string holding one"
+        ))),
+    }
+}
+
+/// Deserializes the buffered data as the given serialized shape
+pub fn from_data<T: DeserializeOwned, E: Error>(data: &Value) -> Result<T, E> {
+    T::deserialize(data).map_err(E::custom)
+}
+
+/// Error of the version missing from the known versions of the model
+pub fn unknown_version<E: Error>(version: u32, known: &[u32]) -> E {
+    let known = known
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    E::custom(format_args!(
+        "unknown model version `{version}`, expected one of: {known}"
+    ))
+}
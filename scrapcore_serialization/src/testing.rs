@@ -0,0 +1,135 @@
+//! Helpers for snapshot testing of game content
+//!
+//! Content directories are rendered into stable, human-readable text, which
+//! can be compared with snapshot testing tools like `insta`:
+//!
+//! ```ignore
+//! #[test]
+//! fn content() {
+//!     let snapshot = snapshot_directory::<PartialGameRegistry>("./content".as_ref());
+//!     insta::assert_snapshot!(snapshot);
+//! }
+//! ```
+//!
+//! Items are rendered with their `Debug` implementations, so items holding
+//! hash maps should use ordered maps to keep snapshots stable
+use std::fmt::Write;
+use std::path::Path;
+
+use itertools::Itertools;
+use slabmap::SlabMapUntypedId;
+
+use crate::registry::loader::load_registry;
+use crate::registry::{LoadableRegistry, RegistryItems, SerializationRegistry};
+use crate::serialization::error::DeserializationError;
+
+/// Loads the directory, and renders either the complete registry, or all
+/// loading errors
+pub fn snapshot_directory<Registry: LoadableRegistry>(root: &Path) -> String
+where
+    Registry::Registry: RegistryItems,
+{
+    match load_registry::<Registry>(root) {
+        Ok(registry) => render_registry(&registry),
+        Err(errors) => render_errors(&errors),
+    }
+}
+
+/// Renders all items of the registry grouped by their kinds, with
+/// collection items sorted by their keys
+///
+/// References to collection items are printed as the item keys rather than
+/// numeric ids. Stale references are printed as ids
+pub fn render_registry<Registry: RegistryItems>(registry: &Registry) -> String {
+    let mut items = vec![];
+    registry.visit_items(&mut |kind, key, item| {
+        let item = resolve_ids(registry, &format!("{item:#?}"));
+        items.push((kind.to_string(), key.cloned(), item))
+    });
+
+    let mut out = String::new();
+    for (kind, items) in &items
+        .into_iter()
+        .sorted()
+        .group_by(|(kind, ..)| kind.clone())
+    {
+        writeln!(out, "=== {kind} ===").unwrap();
+        for (_, key, item) in items {
+            if let Some(key) = key {
+                writeln!(out, "--- {key} ---").unwrap();
+            }
+            writeln!(out, "{item}").unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+/// Replaces ids of collection items in the debug output with the item keys
+fn resolve_ids<Registry: RegistryItems>(registry: &Registry, text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(ID_PREFIX) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let resolved = parse_id(rest).and_then(|(len, value_type, id)| {
+            registry.id_to_key(value_type, id).map(|key| (len, key))
+        });
+        let len = match resolved {
+            Some((len, key)) => {
+                write!(out, "{key:?}").unwrap();
+                len
+            }
+            None => {
+                out.push_str(ID_PREFIX);
+                ID_PREFIX.len()
+            }
+        };
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+const ID_PREFIX: &str = "SlabMapId<";
+
+/// Parses the debug output of an id at the start of the text, in the form of
+/// `SlabMapId<Type>(index)` or `SlabMapId<Type>(index, generation)`
+///
+/// Returns the length of the output, the type name of the map values, and
+/// the id
+fn parse_id(text: &str) -> Option<(usize, &str, SlabMapUntypedId)> {
+    let mut depth = 1;
+    let type_len = text[ID_PREFIX.len()..].find(|c| {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    let type_end = ID_PREFIX.len() + type_len;
+    let args = text[type_end..].strip_prefix(">(")?;
+    let args = &args[..args.find(')')?];
+    let (index, generation) = args.split_once(", ").unwrap_or((args, "0"));
+    let (index, generation) = (index.parse().ok()?, generation.parse().ok()?);
+    let id = SlabMapUntypedId::from_parts(index, generation)?;
+    let len = type_end + ">(".len() + args.len() + ")".len();
+    Some((len, &text[ID_PREFIX.len()..type_end], id))
+}
+
+/// Renders the errors along with their context stacks, innermost context
+/// first
+pub fn render_errors<Registry: SerializationRegistry>(
+    errors: &[DeserializationError<Registry>],
+) -> String {
+    let mut out = String::new();
+    for err in errors {
+        writeln!(out, "error: {}", err.kind).unwrap();
+        for item in &err.stack {
+            writeln!(out, "    {item}").unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    out
+}
@@ -72,6 +72,43 @@ fn serialized_of(ty: &Type) -> Type {
     })
 }
 
+/// Whether the type is a numeric primitive usable in expressions, recognized
+/// by its name
+fn is_numeric(ty: &Type) -> bool {
+    const NUMERIC: [&str; 12] = [
+        "i8", "i16", "i32", "i64", "i128", "u8", "u16", "u32", "u64", "u128", "f32", "f64",
+    ];
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    path.qself.is_none()
+        && path
+            .path
+            .get_ident()
+            .is_some_and(|ident| NUMERIC.iter().any(|name| ident == name))
+}
+
+/// Inner type of the `Option<T>` type, recognized by the last path segment
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>().as_slice() {
+        [syn::GenericArgument::Type(inner)] => Some(inner),
+        _ => None,
+    }
+}
+
 #[proc_macro_derive(DatabaseModel, attributes(model, model_attr, model_serde))]
 pub fn database_model(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -2,20 +2,22 @@ use crate::error::{bail, MacroError};
 use crate::model::attrs::{ModelAttributeConfig, SharedAttributeConfig};
 use crate::model::enums::{process_enum, EnumVariantAttributeInput};
 use crate::model::structs::{process_struct, FieldAttributeInput};
-use crate::{serialized_of, MOD_ERRORS, MOD_REGISTRY, MOD_SERIALIZATION};
-use darling::ast::Data;
+use crate::{is_numeric, option_inner, serialized_of, MOD_ERRORS, MOD_REGISTRY, MOD_SERIALIZATION};
+use darling::ast::{Data, Style};
 use darling::FromDeriveInput;
+use itertools::Itertools;
 use proc_macro2::{Ident, Span, TokenStream};
-use quote::{quote, quote_spanned};
+use quote::{format_ident, quote, quote_spanned};
 use std::borrow::Cow;
 
 use darling::util::SpannedValue;
 use std::ops::Deref;
 use syn::spanned::Spanned;
-use syn::{DeriveInput, Meta, Type, WhereClause};
+use syn::{parse_quote, DeriveInput, Meta, Type, WhereClause};
 
 mod enums;
 mod structs;
+mod versioned;
 
 mod attrs;
 
@@ -24,7 +26,7 @@ mod attrs;
 #[darling(
     attributes(model),
     forward_attrs(model_attr, model_serde),
-    supports(struct_named, enum_newtype, enum_unit)
+    supports(struct_any, enum_named, enum_newtype, enum_unit)
 )]
 pub struct ModelAttributeInput {
     // Internal fields
@@ -52,6 +54,198 @@ impl ModelAttributeConfig {
         }
     }
 
+    /// Serde container attributes for the serialized model
+    ///
+    /// `style` is the shape of the struct fields, or `None` for enums
+    fn serde_attrs(&self, style: Option<Style>) -> Result<TokenStream, MacroError> {
+        const RENAME_RULES: &[&str] = &[
+            "lowercase",
+            "UPPERCASE",
+            "PascalCase",
+            "camelCase",
+            "snake_case",
+            "SCREAMING_SNAKE_CASE",
+            "kebab-case",
+            "SCREAMING-KEBAB-CASE",
+        ];
+
+        let is_enum = style.is_none();
+        let named = style == Some(Style::Struct);
+        let mut attrs = vec![];
+        match &self.rename_all {
+            Some(rule) if !is_enum && !named => {
+                bail!(
+                    rule.span(),
+                    "`rename_all` attribute can not be used on tuple or unit structs"
+                )
+            }
+            Some(rule) => {
+                if !RENAME_RULES.contains(&rule.as_str()) {
+                    bail!(
+                        rule.span(),
+                        "Unknown `rename_all` rule, expected one of: {}",
+                        RENAME_RULES.join(", ")
+                    )
+                }
+                let rule = rule.as_str();
+                attrs.push(quote!(#[serde(rename_all = #rule)]));
+            }
+            None if named && !self.transparent.is_present() => {
+                attrs.push(quote!(#[serde(rename_all = "camelCase")]))
+            }
+            None => {}
+        }
+
+        if !is_enum {
+            if let Some(span) = self
+                .tag
+                .as_ref()
+                .map(|e| e.span())
+                .or_else(|| self.content.as_ref().map(|e| e.span()))
+                .or_else(|| self.untagged.is_present().then(|| self.untagged.span()))
+            {
+                bail!(
+                    span,
+                    "`tag`, `content`, and `untagged` attributes can only be used on enums"
+                )
+            }
+        }
+
+        if self.transparent.is_present() {
+            if is_enum {
+                bail!(
+                    self.transparent.span(),
+                    "`transparent` attribute can only be used on structs"
+                )
+            }
+            attrs.push(quote!(#[serde(transparent)]));
+        }
+
+        if self.untagged.is_present() {
+            if self.tag.is_some() || self.content.is_some() {
+                bail!(
+                    self.untagged.span(),
+                    "`untagged` attribute can not be used at the same time as `tag` or `content` attributes"
+                )
+            }
+            attrs.push(quote!(#[serde(untagged)]));
+        }
+
+        match (&self.tag, &self.content) {
+            (Some(tag), Some(content)) => {
+                let (tag, content) = (tag.as_str(), content.as_str());
+                attrs.push(quote!(#[serde(tag = #tag, content = #content)]));
+            }
+            (Some(tag), None) => {
+                let tag = tag.as_str();
+                attrs.push(quote!(#[serde(tag = #tag)]));
+            }
+            (None, Some(content)) => {
+                bail!(
+                    content.span(),
+                    "`content` attribute requires `tag` attribute to be set"
+                )
+            }
+            (None, None) => {}
+        }
+
+        Ok(quote!(#(#attrs)*))
+    }
+
+    /// Wraps the expression building the model into calls to the
+    /// `validate` and `validate_with_context` functions, if any are provided
+    fn validated(&self, value: TokenStream) -> TokenStream {
+        if self.validate.is_none() && self.validate_with_context.is_none() {
+            return quote!(Ok(#value));
+        }
+
+        let reg = MOD_REGISTRY.deref();
+        let validate = self
+            .validate
+            .as_ref()
+            .map(|func| quote!(#func(&value, registry)?;));
+        let validate_with_context = self.validate_with_context.as_ref().map(|func| {
+            quote! {
+                let item = #reg::PartialRegistry::current_item(registry);
+                #func(&value, item, registry)?;
+            }
+        });
+        quote! {
+            let value = #value;
+            #validate
+            #validate_with_context
+            Ok(value)
+        }
+    }
+
+    /// `ArbitrarySerialized` implementation for the serialized model, if one
+    /// is requested via `arbitrary` attribute
+    ///
+    /// `strategy` produces the expression building the strategy, with the
+    /// key pool available as `pool`
+    fn arbitrary_impl(
+        &self,
+        serialized_name: &Ident,
+        generics: &syn::Generics,
+        strategy: impl FnOnce() -> Result<TokenStream, MacroError>,
+    ) -> Result<Option<TokenStream>, MacroError> {
+        if !self.arbitrary.is_present() {
+            return Ok(None);
+        }
+        if !generics.params.is_empty() {
+            bail!(
+                self.arbitrary.span(),
+                "`arbitrary` attribute can not be used on generic models"
+            )
+        }
+        let ser = MOD_SERIALIZATION.deref();
+        let strategy = strategy()?;
+        Ok(Some(quote! {
+            #[automatically_derived]
+            impl #ser::arbitrary::ArbitrarySerialized for #serialized_name {
+                fn arbitrary_serialized(pool: &#ser::arbitrary::KeyPool) -> #ser::arbitrary::BoxedStrategy<Self> {
+                    #strategy
+                }
+            }
+        }))
+    }
+
+    /// `RemapIds` implementation for the model, if one is requested via
+    /// `remap_ids` attribute
+    ///
+    /// `body` produces the statements remapping ids of `self`, with the
+    /// remap table available as `remap`
+    fn remap_ids_impl(
+        &self,
+        model_name: &Ident,
+        generics: &syn::Generics,
+        body: impl FnOnce() -> TokenStream,
+    ) -> Result<Option<TokenStream>, MacroError> {
+        if !self.remap_ids.is_present() {
+            return Ok(None);
+        }
+        if !generics.params.is_empty() {
+            bail!(
+                self.remap_ids.span(),
+                "`remap_ids` attribute can not be used on generic models"
+            )
+        }
+        let reg = MOD_REGISTRY.deref();
+        let body = body();
+        Ok(Some(quote! {
+            #[automatically_derived]
+            impl #reg::remap::RemapIds for #model_name {
+                fn remap_ids(
+                    &mut self,
+                    remap: &#reg::remap::RegistryRemap,
+                ) -> Result<(), #reg::remap::DanglingIdError> {
+                    #body
+                    Ok(())
+                }
+            }
+        }))
+    }
+
     fn where_clauses(&self) -> impl Iterator<Item = TokenStream> + '_ {
         self.extra_conditions.iter().map(|clause| {
             let predicates = &clause.predicates;
@@ -96,6 +290,42 @@ fn fallthrough(attrs: &[syn::Attribute]) -> Vec<TokenStream> {
     inner_attrs
 }
 
+/// Strategy generating every one of the serialized `types`, and building the
+/// value with `construct` from the generated values bound to the provided
+/// identifiers
+fn arbitrary_fields(
+    types: &[TokenStream],
+    construct: impl FnOnce(&[Ident]) -> TokenStream,
+) -> TokenStream {
+    let ser = MOD_SERIALIZATION.deref();
+    let bindings = (0..types.len())
+        .map(|i| format_ident!("field_{}", i))
+        .collect_vec();
+    // Strategies are nested into pairs, since tuple strategies only go up to
+    // a limited number of elements
+    let strategy = types.iter().rev().fold(
+        quote!(#ser::arbitrary::Just(())),
+        |rest, ty| quote!((<#ty as #ser::arbitrary::ArbitrarySerialized>::arbitrary_serialized(pool), #rest)),
+    );
+    let pattern = bindings
+        .iter()
+        .rev()
+        .fold(quote!(()), |rest, binding| quote!((#binding, #rest)));
+    let value = construct(&bindings);
+    quote! {
+        #ser::arbitrary::Strategy::boxed(
+            #ser::arbitrary::Strategy::prop_map(#strategy, |#pattern| #value)
+        )
+    }
+}
+
+/// Statement remapping ids of the value behind the `value` reference,
+/// skipped for `raw` fields since their values never hold ids
+fn remap_field(config: &SharedAttributeConfig, value: TokenStream) -> Option<TokenStream> {
+    let reg = MOD_REGISTRY.deref();
+    (!config.raw.is_present()).then(|| quote!(#reg::remap::RemapIds::remap_ids(#value, remap)?;))
+}
+
 fn edit_where_clause(
     clause: Option<&WhereClause>,
     fields_conditions: impl IntoIterator<Item = TokenStream>,
@@ -117,16 +347,30 @@ fn edit_where_clause(
 
 impl SharedAttributeConfig {
     fn serialized_ty<'a>(&'a self, field_ty: &'a Type) -> Result<Cow<'a, Type>, MacroError> {
-        // "raw" fields use their target type directly, otherwise lookup
-        // `#[model(ty=T)]` or use `SerializationFallback`
+        // "raw" fields use their target type directly, "expr" fields are
+        // wrapped into `NumericExpr`, otherwise lookup `#[model(ty=T)]`,
+        // wrap numbers into `ConstOr`, or use `SerializationFallback`
         let field_ty = self.from.as_ref().unwrap_or(field_ty);
         let ty = if self.raw.is_present() {
-            if self.custom_ty.is_some() || self.with.is_some() {
-                bail!(self.raw.span(), "`raw` attribute field can not be used at the same time as `ty` or `with` attributes")
+            if self.custom_ty.is_some() || self.with.is_some() || self.expr.is_present() {
+                bail!(self.raw.span(), "`raw` attribute field can not be used at the same time as `ty`, `with`, or `expr` attributes")
             }
             Cow::Borrowed(field_ty)
+        } else if self.expr.is_present() {
+            if self.custom_ty.is_some() {
+                bail!(
+                    self.expr.span(),
+                    "`expr` attribute field can not be used at the same time as `ty` attribute"
+                )
+            }
+            let ser = MOD_SERIALIZATION.deref();
+            Cow::Owned(syn::parse2(quote!(#ser::expr::NumericExpr<#field_ty>))?)
         } else if let Some(ty) = &self.custom_ty {
             Cow::Borrowed(ty)
+        } else if let Some(ty) = self.constant_ty(field_ty) {
+            Cow::Owned(ty)
+        } else if let Some(ty) = option_inner(field_ty).and_then(|ty| self.constant_ty(ty)) {
+            Cow::Owned(syn::parse2(quote!(Option<#ty>))?)
         } else {
             Cow::Owned(serialized_of(field_ty))
         };
@@ -134,6 +378,36 @@ impl SharedAttributeConfig {
         Ok(ty)
     }
 
+    /// `ConstOr` wrapper of the numeric primitive type, allowing the value to
+    /// reference a named constant
+    ///
+    /// Fields with custom deserialization functions keep their serialized
+    /// type, since the function expects it
+    fn constant_ty(&self, ty: &Type) -> Option<Type> {
+        if self.with.is_some() || !is_numeric(ty) {
+            return None;
+        }
+        let ser = MOD_SERIALIZATION.deref();
+        Some(parse_quote!(#ser::constant::ConstOr<#ty>))
+    }
+
+    /// Serialized type of the inner value of `Option` fields, if the
+    /// serialized type of the field is an `Option` too
+    fn serialized_option_inner(&self, field_ty: &Type) -> Option<Type> {
+        if self.raw.is_present() || self.expr.is_present() {
+            return None;
+        }
+        if let Some(ty) = &self.custom_ty {
+            return option_inner(ty).cloned();
+        }
+        let field_ty = self.from.as_ref().unwrap_or(field_ty);
+        let inner = option_inner(field_ty)?;
+        Some(
+            self.constant_ty(inner)
+                .unwrap_or_else(|| serialized_of(inner)),
+        )
+    }
+
     fn where_condition(
         &self,
         original_type: &Type,
@@ -147,19 +421,15 @@ impl SharedAttributeConfig {
         {
             bail!(Span::call_site(), "`id` and `id_of` attributes can not be used with `no_condition`, `with`, or `raw` attributes");
         }
-        if self.id.is_present() {
-            if self.id_of.is_some() {
-                bail!(
-                    self.id.span(),
-                    "`id` and `id_of` attributes can not be used together"
-                );
-            }
-            return Ok(Some(quote! {
-                Registry: #reg::PartialCollectionHolder<<#original_type as #reg::reverse_id::ReverseId>::Item>
-            }));
-        } else if let Some(of) = &self.id_of {
+        if self.id.is_present() && self.id_of.is_some() {
+            bail!(
+                self.id.span(),
+                "`id` and `id_of` attributes can not be used together"
+            );
+        }
+        if let Some(item) = self.referenced_item(original_type) {
             return Ok(Some(quote! {
-                Registry: #reg::PartialCollectionHolder<#of>
+                Registry: #reg::PartialCollectionHolder<#item>
             }));
         }
 
@@ -173,18 +443,41 @@ impl SharedAttributeConfig {
         )
     }
 
+    /// Collection item type referenced by the `id` or `id_of` field
+    fn referenced_item(&self, original_type: &Type) -> Option<TokenStream> {
+        let reg = MOD_REGISTRY.deref();
+        let original_type = self.from.as_ref().unwrap_or(original_type);
+        if let Some(of) = &self.id_of {
+            Some(quote!(#of))
+        } else if self.id.is_present() {
+            Some(quote!(<#original_type as #reg::reverse_id::ReverseId>::Item))
+        } else {
+            None
+        }
+    }
+
+    /// Deserializes the field from `varname`, applying validators and the
+    /// `from` conversion
+    ///
+    /// With `value` provided, it's used as the deserialized value instead,
+    /// and only goes through validators and the conversion
     fn deserialization_code(
         &self,
         field_ty: &Type,
         varname: &Ident,
         context: Option<TokenStream>,
+        value: Option<TokenStream>,
     ) -> Result<TokenStream, MacroError> {
         let ser = MOD_SERIALIZATION.deref();
         let err = MOD_ERRORS.deref();
 
         let target_type = self.from.as_ref().unwrap_or(field_ty);
         let mut blocks = vec![];
-        if self.raw.is_present() {
+        if let Some(value) = value {
+            blocks.push(quote! {
+                let #varname: #target_type = #value;
+            })
+        } else if self.raw.is_present() {
             blocks.push(quote! {
                 let #varname: #target_type = #varname;
             })
@@ -210,6 +503,49 @@ impl SharedAttributeConfig {
             })
         }
 
+        if self.finite.is_present() {
+            blocks.push(quote! {
+                let #varname: #target_type = #ser::validators::ApplyFinite::apply(#varname)?;
+            })
+        }
+
+        if self.non_empty.is_present() {
+            blocks.push(quote! {
+                let #varname: #target_type = #ser::validators::ApplyNonEmpty::apply(#varname)?;
+            })
+        }
+
+        if let Some(len_min) = &self.len_min {
+            blocks.push(quote! {
+                let #varname: #target_type = #ser::validators::ApplyLenMin::apply(#varname, #len_min)?;
+            })
+        }
+
+        if let Some(len_max) = &self.len_max {
+            blocks.push(quote! {
+                let #varname: #target_type = #ser::validators::ApplyLenMax::apply(#varname, #len_max)?;
+            })
+        }
+
+        if let Some(pattern) = &self.regex {
+            if let Err(err) = regex::Regex::new(pattern) {
+                bail!(pattern.span(), "Invalid regex pattern: {err}")
+            }
+            let pattern = pattern.as_str();
+            blocks.push(quote! {
+                let #varname: #target_type = {
+                    static REGEX: #ser::validators::LazyRegex = #ser::validators::LazyRegex::new(#pattern);
+                    #ser::validators::ApplyRegex::apply(#varname, &REGEX)?
+                };
+            })
+        }
+
+        if let Some(one_of) = &self.one_of {
+            blocks.push(quote! {
+                let #varname: #target_type = #ser::validators::ApplyOneOf::apply(#varname, &#one_of)?;
+            })
+        }
+
         if let Some(from) = &self.from {
             blocks.push(quote! {
                 let #varname: #field_ty = <#field_ty as From<#from>>::from(#varname);
@@ -1,4 +1,4 @@
-use darling::util::Flag;
+use darling::util::{Flag, SpannedValue};
 use darling::FromMeta;
 use proc_macro2::Ident;
 use syn::{Expr, Path, Type};
@@ -10,10 +10,71 @@ pub struct ModelAttributeConfig {
     pub name: Option<Ident>,
     /// Skips deriving `schemars` schema
     pub no_schema: Flag,
+    /// Implements `ArbitrarySerialized` for the serialized model, generating
+    /// `proptest` strategies for it
+    ///
+    /// Requires `proptest` feature of the `scrapcore_serialization` crate, and
+    /// all serialized field types to implement `ArbitrarySerialized`
+    pub arbitrary: Flag,
+    /// Implements `RemapIds` for the model, updating ids held by its fields
+    /// after registry collections are compacted or reordered
+    ///
+    /// Requires all field types except for `raw` fields to implement
+    /// `RemapIds`
+    pub remap_ids: Flag,
     /// Extra "where" conditions for the `DeserializeModel` implementation
     #[darling(multiple)]
     #[darling(rename = "condition")]
     pub extra_conditions: Vec<syn::WhereClause>,
+    /// Path to the validation function, called with the fully deserialized
+    /// value
+    ///
+    /// Validation function must have signature
+    /// `fn(&T, &mut Registry) -> Result<(), E>`, where `T` is the model type,
+    /// and `E` is convertible into `DeserializationError<Registry>`
+    ///
+    /// Useful for rules spanning multiple fields
+    pub validate: Option<Path>,
+    /// Same as `validate`, with the function also receiving the collection
+    /// item being deserialized
+    ///
+    /// Validation function must have signature
+    /// `fn(&T, Option<ItemContext<Registry::ItemKind>>, &mut Registry) -> Result<(), E>`.
+    /// `ItemContext` holds the key of the collection item being deserialized,
+    /// see `PartialRegistry::current_item`
+    pub validate_with_context: Option<Path>,
+
+    /// Overrides serde `rename_all` rule of the serialized model
+    ///
+    /// Defaults to `camelCase` for structs, and to no renaming for enums
+    pub rename_all: Option<SpannedValue<String>>,
+    /// Uses internal (or adjacent, when combined with `content`) tagging for
+    /// the serialized enum, same as serde `tag` attribute
+    pub tag: Option<SpannedValue<String>>,
+    /// Uses adjacent tagging for the serialized enum, same as serde `content`
+    /// attribute. Requires `tag` to be set
+    pub content: Option<SpannedValue<String>>,
+    /// Makes the serialized enum untagged, same as serde `untagged` attribute
+    pub untagged: Flag,
+    /// Serializes the single-field struct as its only field, same as serde
+    /// `transparent` attribute
+    pub transparent: Flag,
+
+    /// Version of the serialized model. Versioned models accept a `"version"`
+    /// field in the serialized data, either a string or an integer, with
+    /// data lacking it being treated as the latest version. Unknown versions
+    /// are rejected
+    ///
+    /// Requires a self-describing format, since the data is buffered before
+    /// picking the serialized shape
+    pub version: Option<SpannedValue<u32>>,
+    /// Historical serialized shapes of the model, newest first, each one
+    /// being one version below the previous one
+    ///
+    /// Every shape must be convertible via `From` into the next newer one,
+    /// with the newest one converting into the latest serialized model
+    #[darling(multiple)]
+    pub upgrade_from: Vec<Type>,
 }
 
 /// Attribute fields for both struct and enum fields
@@ -40,9 +101,17 @@ pub struct SharedAttributeConfig {
     /// where `T` is the field type, and so this flag is **conflicting** with
     /// `ty` and `with` flags
     ///
-    /// Useful for types from external crates that do not implement `DeserializeModel`
+    /// Useful for types from external crates that do not implement
+    /// `DeserializeModel`, and for numeric fields that should not accept
+    /// `"$name"` constant references
     pub raw: Flag,
 
+    /// Allows the numeric field to be written as an expression string, see
+    /// `NumericExpr` for the supported syntax
+    ///
+    /// This flag is **conflicting** with `raw` and `ty` flags
+    pub expr: Flag,
+
     /// Forces the field to be deserialized as a given type,
     /// and then converted into the field type using the `From` impl
     pub from: Option<Type>,
@@ -51,6 +120,22 @@ pub struct SharedAttributeConfig {
     pub min: Option<Expr>,
     /// Applies max validator to the field
     pub max: Option<Expr>,
+    /// Applies minimum length validator to the string or collection field
+    pub len_min: Option<Expr>,
+    /// Applies maximum length validator to the string or collection field
+    pub len_max: Option<Expr>,
+    /// Requires the string or collection field to not be empty
+    pub non_empty: Flag,
+    /// Requires the string field to match the given regex pattern
+    ///
+    /// Requires `regex` feature of the `scrapcore_serialization` crate. The
+    /// pattern is checked at compile time
+    pub regex: Option<SpannedValue<String>>,
+    /// Requires the field to be equal to one of the values in the provided
+    /// array, like `one_of = ["a", "b"]`
+    pub one_of: Option<Expr>,
+    /// Requires the float field to not be NaN or infinite
+    pub finite: Flag,
 
     /// Marks the field as "Id" field, emitting a different `where` condition.
     ///
@@ -59,6 +144,9 @@ pub struct SharedAttributeConfig {
     /// Relies on `ReverseId` trait:
     /// `where Registry: PartialCollectionHolder<<T as ReverseId>::Item>`
     /// where `T` is the field type
+    ///
+    /// Field schema is annotated with `x-scrapcore-ref` extension holding
+    /// the referenced item kind name
     pub id: Flag,
 
     /// Same as [id], but uses the provided type instead of relying on a trait
@@ -75,12 +163,77 @@ pub struct SharedAttributeConfig {
     pub no_condition: Flag,
 }
 
+impl SharedAttributeConfig {
+    /// Whether none of the attributes were provided
+    pub fn is_empty(&self) -> bool {
+        let flags = [
+            &self.raw,
+            &self.expr,
+            &self.non_empty,
+            &self.finite,
+            &self.id,
+            &self.no_condition,
+        ];
+        flags.iter().all(|flag| !flag.is_present())
+            && self.custom_ty.is_none()
+            && self.with.is_none()
+            && self.from.is_none()
+            && self.min.is_none()
+            && self.max.is_none()
+            && self.len_min.is_none()
+            && self.len_max.is_none()
+            && self.regex.is_none()
+            && self.one_of.is_none()
+            && self.id_of.is_none()
+    }
+}
+
+/// Default value of the struct field, used when the field is missing from
+/// the serialized data
+#[derive(Debug)]
+pub enum FieldDefault {
+    /// `#[model(default)]`, uses `Default` impl of the field type
+    Inherit,
+    /// `#[model(default = expr)]`, uses the expression converted into the
+    /// serialized field type
+    ///
+    /// Unlike other expression attributes, string literals are kept as-is
+    Explicit(Expr),
+}
+
+impl FromMeta for FieldDefault {
+    fn from_word() -> darling::Result<Self> {
+        Ok(FieldDefault::Inherit)
+    }
+
+    fn from_expr(expr: &Expr) -> darling::Result<Self> {
+        Ok(FieldDefault::Explicit(expr.clone()))
+    }
+}
+
 /// Attribute fields for struct fields
 #[derive(Debug, FromMeta)]
 pub struct StructFieldAttributeConfig {
     /// Generated AsRef implementation for marked struct to value of this field
     pub as_ref: Flag,
+    /// Makes the field optional in the serialized data
+    ///
+    /// `#[model(default)]` uses the `Default` impl of the field type (or of
+    /// the `from` type) directly, skipping deserialization, but still
+    /// applying validators
+    ///
+    /// `#[model(default = expr)]` converts the expression into the serialized
+    /// field type via `Into`, and then deserializes it as if it was present in
+    /// the data. This allows using item keys (like `default = "core:alex"`)
+    /// for id fields and asset names for asset fields
+    ///
+    /// Only missing fields get the default, an explicit `null` is
+    /// deserialized as the field value
+    pub default: Option<FieldDefault>,
     /// Custom name for the serialized field
+    ///
+    /// Parsed for compatibility, but not applied to serialized structs yet
+    #[allow(dead_code)]
     pub rename: Option<Ident>,
 
     /// Shared config, see [SharedAttributeConfig] for available attributes
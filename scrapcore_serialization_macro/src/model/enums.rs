@@ -1,33 +1,29 @@
 use crate::error::{bail, MacroError};
 use crate::model::attrs::{EnumVariantAttributeConfig, ModelAttributeConfig};
-use crate::model::{edit_where_clause, fallthrough};
+use crate::model::structs::{collect_fields, FieldAttributeInput};
+use crate::model::{arbitrary_fields, edit_where_clause, fallthrough, remap_field};
 use crate::{MOD_ERRORS, MOD_REGISTRY, MOD_SERIALIZATION};
-use darling::ast::Fields;
+use darling::ast::{Fields, Style};
 use darling::util::SpannedValue;
-use darling::{FromField, FromMeta, FromVariant};
+use darling::{FromMeta, FromVariant};
 use itertools::Itertools;
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote, quote_spanned};
 use std::ops::Deref;
+use syn::spanned::Spanned;
 use syn::{GenericParam, TypeParam};
 
 #[derive(Debug, FromVariant)]
 #[darling(attributes(model))]
 pub struct EnumVariantAttributeInput {
     ident: Ident,
-    fields: Fields<EnumVariantField>,
+    fields: Fields<SpannedValue<FieldAttributeInput>>,
     attrs: Vec<syn::Attribute>,
 
     #[darling(flatten)]
     config: EnumVariantAttributeConfig,
 }
 
-#[derive(Debug, FromField)]
-struct EnumVariantField {
-    ident: Option<Ident>,
-    ty: syn::Type,
-}
-
 pub fn process_enum(
     model_name: Ident,
     mut generics: syn::Generics,
@@ -35,6 +31,19 @@ pub fn process_enum(
     config: ModelAttributeConfig,
     variants: Vec<SpannedValue<EnumVariantAttributeInput>>,
 ) -> Result<TokenStream, MacroError> {
+    if let Some(version) = &config.version {
+        bail!(
+            version.span(),
+            "Only structs with named fields can be versioned"
+        )
+    }
+    if let Some(ty) = config.upgrade_from.first() {
+        bail!(
+            ty.span(),
+            "`upgrade_from` attribute can only be used on versioned structs"
+        )
+    }
+
     let model_fallthrough_attrs = fallthrough(&attributes);
     let serialized_name = config
         .name
@@ -46,7 +55,101 @@ pub fn process_enum(
     let ser = MOD_SERIALIZATION.deref();
     let err = MOD_ERRORS.deref();
 
+    // Struct-like variants use the model-level `rename_all` rule for their
+    // fields, defaulting to `camelCase` same as structs
+    let fields_rename_rule = config
+        .rename_all
+        .as_ref()
+        .map(|rule| rule.as_str())
+        .unwrap_or("camelCase");
+    let schema_attrs = !config.no_schema.is_present() && generics.params.is_empty();
+    let mut default_fns = vec![];
+
     let variants = variants.iter().map(|variant| {
+        let fallthrough_attrs = fallthrough(&variant.attrs);
+        let variant_name = variant.config.rename.as_ref().unwrap_or(&variant.ident);
+        let ident = &variant.ident;
+
+        if variant.fields.style == Style::Struct {
+            if !variant.config.config.is_empty() {
+                bail!(
+                    variant.span(),
+                    "Field attributes of struct-like variants must be placed on the variant fields"
+                )
+            }
+            let fields = collect_fields(
+                &format_ident!("{}_{}", model_name, ident),
+                &variant.fields,
+                schema_attrs,
+                &mut default_fns,
+            )?;
+            if let Some(field) = variant.fields.iter().find(|f| f.config.as_ref.is_present()) {
+                bail!(
+                    field.span(),
+                    "`as_ref` attribute can not be used on enum variant fields"
+                )
+            }
+
+            let definitions = fields.iter().map(|f| &f.definition);
+            let serialized_variant = quote_spanned! {variant.span()=>
+                #(#fallthrough_attrs)*
+                #[serde(rename_all = #fields_rename_rule)]
+                #variant_name { #(#definitions),* },
+            };
+
+            let bindings = fields
+                .iter()
+                .map(|f| format_ident!("field_{}", f.member))
+                .collect_vec();
+            let members = fields.iter().map(|f| &f.member);
+            let initializers: Vec<TokenStream> = fields
+                .iter()
+                .zip(&bindings)
+                .map(|(f, binding)| f.initializer(quote!(#binding)))
+                .try_collect()?;
+            let deserialization_match = quote_spanned! {variant.span()=>
+                Self::#variant_name { #(#members: #bindings),* } => #err::s_try(
+                    &mut *registry,
+                    |registry: &mut Registry| Ok(#model_name::#ident { #(#initializers)* }),
+                    Some(|| #err::DeserializationErrorStackItem::Variant(stringify!(#variant_name))),
+                )?,
+            };
+
+            let where_condition: Vec<_> = fields
+                .iter()
+                .map(|f| f.config.where_condition(f.original_type, &f.serialized_type))
+                .filter_map_ok(|e| e)
+                .try_collect()?;
+            let where_condition = (!where_condition.is_empty()).then(|| quote!(#(#where_condition),*));
+
+            let types = fields.iter().map(|f| f.field_type()).collect_vec();
+            let strategy = arbitrary_fields(&types, |bindings| {
+                let members = fields.iter().map(|f| &f.member);
+                quote!(Self::#variant_name { #(#members: #bindings),* })
+            });
+
+            let (members, remaps): (Vec<_>, Vec<_>) = fields
+                .iter()
+                .zip(&bindings)
+                .filter_map(|(f, binding)| {
+                    let member = &f.member;
+                    let remap = remap_field(f.config, quote!(#binding))?;
+                    Some((quote!(#member: #binding), remap))
+                })
+                .unzip();
+            let remap_match = quote_spanned! {variant.span()=>
+                #model_name::#ident { #(#members,)* .. } => { #(#remaps)* }
+            };
+
+            return Result::<_, MacroError>::Ok((
+                serialized_variant,
+                deserialization_match,
+                where_condition,
+                strategy,
+                remap_match,
+            ));
+        }
+
         let field = match variant.fields.iter().at_most_one() {
             Ok(field) => field,
             Err(_) => {
@@ -57,15 +160,8 @@ pub fn process_enum(
             }
         };
 
-        let fallthrough_attrs = fallthrough(&variant.attrs);
-        let variant_name = variant.config.rename.as_ref().unwrap_or(&variant.ident);
-
-        let (serialized_variant, deserialization_match, where_condition) =
+        let (serialized_variant, deserialization_match, where_condition, strategy, remap_match) =
             if let Some(field) = field {
-                if field.ident.is_some() {
-                    bail!(variant.span(), "Only newtype enums are supported");
-                }
-
                 let serialized_ty = variant.config.config.serialized_ty(&field.ty)?;
 
                 let serialized_variant = quote_spanned! {variant.span()=>
@@ -81,10 +177,11 @@ pub fn process_enum(
                 Some(
                     quote!(#err::DeserializationErrorStackItem::Variant(stringify!(#variant_name))),
                 ),
+                None,
             )?;
 
                 let deserialization_match = quote_spanned! {variant.span()=>
-                    Self::#variant_name(#item_var) => #model_name::#variant_name(#deser_code),
+                    Self::#variant_name(#item_var) => #model_name::#ident(#deser_code),
                 };
 
                 let where_condition = variant
@@ -92,37 +189,79 @@ pub fn process_enum(
                     .config
                     .where_condition(&field.ty, &serialized_ty)?;
 
-                (serialized_variant, deserialization_match, where_condition)
+                let strategy = arbitrary_fields(&[quote!(#serialized_ty)], |bindings| {
+                    quote!(Self::#variant_name(#(#bindings),*))
+                });
+
+                let remap_match = match remap_field(&variant.config.config, quote!(#item_var)) {
+                    Some(remap) => quote_spanned! {variant.span()=>
+                        #model_name::#ident(#item_var) => { #remap }
+                    },
+                    None => quote_spanned! {variant.span()=>
+                        #model_name::#ident(_) => {}
+                    },
+                };
+
+                (serialized_variant, deserialization_match, where_condition, strategy, remap_match)
             } else {
                 let serialized_variant = quote_spanned! {variant.span()=>
                     #(#fallthrough_attrs)*
                     #variant_name,
                 };
                 let deserialization_match = quote_spanned! {variant.span()=>
-                    Self::#variant_name => #model_name::#variant_name,
+                    Self::#variant_name => #model_name::#ident,
                 };
                 let where_condition = None;
-                (serialized_variant, deserialization_match, where_condition)
+                let strategy = arbitrary_fields(&[], |_| quote!(Self::#variant_name));
+                let remap_match = quote_spanned! {variant.span()=>
+                    #model_name::#ident => {}
+                };
+                (serialized_variant, deserialization_match, where_condition, strategy, remap_match)
             };
 
-        Result::<(TokenStream, TokenStream, Option<TokenStream>), MacroError>::Ok((
+        Result::<_, MacroError>::Ok((
             serialized_variant,
             deserialization_match,
             where_condition,
+            strategy,
+            remap_match,
         ))
     });
 
-    let (members, deser, where_conditions) = itertools::process_results(variants, |iter| {
-        iter.multiunzip::<(Vec<_>, Vec<_>, Vec<_>)>()
-    })?;
+    let (members, deser, where_conditions, strategies, remap_matches) =
+        itertools::process_results(variants, |iter| {
+            iter.multiunzip::<(Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>)>()
+        })?;
 
     let where_conditions = where_conditions.into_iter().flatten();
 
+    let arbitrary = config.arbitrary_impl(&serialized_name, &generics, || {
+        if strategies.is_empty() {
+            bail!(
+                model_name.span(),
+                "`arbitrary` attribute can not be used on enums without variants"
+            )
+        }
+        Ok(quote! {
+            #ser::arbitrary::Strategy::boxed(#ser::arbitrary::Union::new([#(#strategies),*]))
+        })
+    })?;
+
+    let remap_ids = config.remap_ids_impl(&model_name, &generics, || {
+        quote! {
+            match self {
+                #(#remap_matches)*
+            }
+        }
+    })?;
+
     let schema_derive = config.schema_derive();
+    let serde_attrs = config.serde_attrs(None)?;
     let (gen_imp, gen_ty, gen_wher) = generics.split_for_impl();
     let defs = quote! {
         #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
         #schema_derive
+        #serde_attrs
         #(#model_fallthrough_attrs)*
         pub enum #serialized_name {
             #(#members)*
@@ -133,6 +272,12 @@ pub fn process_enum(
             type Fallback = #serialized_name;
         }
 
+        #(#default_fns)*
+
+        #arbitrary
+
+        #remap_ids
+
         #[automatically_derived]
         impl #gen_imp  AsRef<#model_name #gen_ty> for #model_name #gen_ty #gen_wher {
             fn as_ref(&self) -> &#model_name {
@@ -160,13 +305,17 @@ pub fn process_enum(
 
     let where_condition = edit_where_clause(gen_wher, where_conditions, config.where_clauses());
 
+    let construct = config.validated(quote! {
+        match self {
+            #(#deser)*
+        }
+    });
+
     let deser_code = quote! {
         #[automatically_derived]
         impl #gen_imp #ser::DeserializeModel<#model_name, Registry> for #serialized_name #gen_ty #where_condition {
             fn deserialize(self, registry: &mut Registry) -> Result<#model_name, #err::DeserializationError<Registry>> {
-                Ok(match self {
-                    #(#deser)*
-                })
+                #construct
             }
         }
     };
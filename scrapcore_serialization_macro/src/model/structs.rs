@@ -1,11 +1,12 @@
 use crate::error::{bail, MacroError};
 use crate::model::attrs::{
-    ModelAttributeConfig, SharedAttributeConfig, StructFieldAttributeConfig,
+    FieldDefault, ModelAttributeConfig, SharedAttributeConfig, StructFieldAttributeConfig,
 };
-use crate::model::{edit_where_clause, fallthrough};
+use crate::model::versioned::{process_versions, versioned_name};
+use crate::model::{arbitrary_fields, edit_where_clause, fallthrough, remap_field};
 use crate::{MOD_ERRORS, MOD_REGISTRY, MOD_SERIALIZATION};
 use convert_case::{Case, Casing};
-use darling::ast::Fields;
+use darling::ast::{Fields, Style};
 use darling::util::SpannedValue;
 use darling::{FromField, FromMeta};
 use itertools::Itertools;
@@ -14,25 +15,30 @@ use quote::{format_ident, quote, quote_spanned};
 use std::borrow::Cow;
 use std::ops::Deref;
 use syn::spanned::Spanned;
-use syn::{GenericParam, Ident, Type, TypeParam};
+use syn::{GenericParam, Ident, Member, Type, TypeParam};
 
 #[derive(Debug, FromField)]
 #[darling(attributes(model), forward_attrs(model_attr, model_serde))]
 pub struct FieldAttributeInput {
-    ident: Option<syn::Ident>,
-    ty: Type,
+    pub(super) ident: Option<syn::Ident>,
+    pub(super) ty: Type,
     attrs: Vec<syn::Attribute>,
     #[darling(flatten)]
-    config: StructFieldAttributeConfig,
+    pub(super) config: StructFieldAttributeConfig,
 }
 
+/// Processed struct field, also used for fields of struct-like enum variants
 #[derive(Debug)]
-struct FieldData<'a> {
-    name: Ident,
-    original_type: &'a Type,
-    serialized_type: Cow<'a, Type>,
-    definition: TokenStream,
-    config: &'a SharedAttributeConfig,
+pub(super) struct FieldData<'a> {
+    /// Model field, either a name or an index for tuple structs
+    pub(super) member: Member,
+    /// Error stack item for the field
+    pub(super) context: TokenStream,
+    pub(super) original_type: &'a Type,
+    pub(super) serialized_type: Cow<'a, Type>,
+    pub(super) definition: TokenStream,
+    pub(super) default: Option<&'a FieldDefault>,
+    pub(super) config: &'a SharedAttributeConfig,
 }
 
 pub fn process_struct(
@@ -42,7 +48,6 @@ pub fn process_struct(
     config: ModelAttributeConfig,
     struct_fields: Fields<SpannedValue<FieldAttributeInput>>,
 ) -> Result<TokenStream, MacroError> {
-    let mut fields = Vec::new();
     let model_fallthrough_attrs = fallthrough(&attributes);
 
     let serialized_name = config
@@ -52,14 +57,25 @@ pub fn process_struct(
         .unwrap_or_else(|| format_ident!("{}Serialized", model_name));
 
     let mut as_refs = vec![];
+    let mut default_fns = vec![];
 
-    for field in &struct_fields.fields {
-        let Some(name) = &field.ident else {
-            bail!(field.ty.span(), "All model fields must be named")
-        };
-        let ty = &field.ty;
+    let style = struct_fields.style;
+    if config.transparent.is_present() && struct_fields.len() != 1 {
+        bail!(
+            config.transparent.span(),
+            "`transparent` models must have exactly one field"
+        )
+    }
 
+    let err = MOD_ERRORS.deref();
+
+    let schema_attrs = !config.no_schema.is_present() && generics.params.is_empty();
+    let fields = collect_fields(&model_name, &struct_fields, schema_attrs, &mut default_fns)?;
+
+    for (field, data) in struct_fields.iter().zip(&fields) {
         if field.config.as_ref.is_present() {
+            let ty = &field.ty;
+            let name = &data.member;
             as_refs.push(quote_spanned! {name.span()=>
                 #[automatically_derived]
                 impl AsRef<#ty> for #model_name {
@@ -69,31 +85,65 @@ pub fn process_struct(
                 }
             })
         }
-
-        let serialized_type = field.config.config.serialized_ty(ty)?;
-        let fallthrough_attrs = fallthrough(&field.attrs);
-        let definition = quote_spanned!(name.span()=>
-            #(#fallthrough_attrs)*
-            #name: #serialized_type
-        );
-
-        let field_data = FieldData {
-            name: name.clone(),
-            definition,
-            original_type: ty,
-            serialized_type,
-            config: &field.config.config,
-        };
-
-        fields.push(field_data)
     }
 
     let tokens = fields.iter().map(|e| &e.definition);
     let schema_derive = config.schema_derive();
+    let serde_attrs = config.serde_attrs(Some(style))?;
 
     let _reg = MOD_REGISTRY.deref();
     let ser = MOD_SERIALIZATION.deref();
-    let err = MOD_ERRORS.deref();
+
+    let body = match style {
+        Style::Struct => quote!({ #(#tokens),* }),
+        Style::Tuple => quote!(( #(#tokens),* );),
+        Style::Unit => quote!(;),
+    };
+
+    let (versions, fallback_name) = if config.version.is_some() {
+        if style != Style::Struct {
+            bail!(
+                model_name.span(),
+                "Only structs with named fields can be versioned"
+            )
+        }
+        (
+            Some(process_versions(
+                &model_name,
+                &serialized_name,
+                &generics,
+                &config,
+            )?),
+            versioned_name(&serialized_name),
+        )
+    } else if let Some(ty) = config.upgrade_from.first() {
+        bail!(
+            ty.span(),
+            "`upgrade_from` attribute requires `version` attribute to be set"
+        )
+    } else {
+        (None, serialized_name.clone())
+    };
+
+    let arbitrary = config.arbitrary_impl(&serialized_name, &generics, || {
+        let types = fields.iter().map(FieldData::field_type).collect_vec();
+        Ok(arbitrary_fields(&types, |bindings| match style {
+            Style::Struct => {
+                let members = fields.iter().map(|f| &f.member);
+                quote!(Self { #(#members: #bindings),* })
+            }
+            Style::Tuple => quote!(Self(#(#bindings),*)),
+            Style::Unit => quote!(Self),
+        }))
+    })?;
+
+    let remap_ids = config.remap_ids_impl(&model_name, &generics, || {
+        let fields = fields.iter().filter_map(|f| {
+            let member = &f.member;
+            remap_field(f.config, quote!(&mut self.#member))
+        });
+        quote!(#(#fields)*)
+    })?;
 
     let (gen_imp, gen_ty, gen_wher) = generics.split_for_impl();
 
@@ -101,18 +151,22 @@ pub fn process_struct(
         #(#model_fallthrough_attrs)*
         #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
         #schema_derive
-        #[serde(rename_all = "camelCase")]
-        pub struct #serialized_name #generics {
-            #(#tokens),*
-        }
+        #serde_attrs
+        pub struct #serialized_name #generics #body
 
         #[automatically_derived]
         impl #gen_imp #ser::SerializationFallback for #model_name #gen_ty #gen_wher {
-            type Fallback = #serialized_name;
+            type Fallback = #fallback_name;
         }
 
         #(#as_refs)*
 
+        #(#default_fns)*
+
+        #arbitrary
+
+        #remap_ids
+
         #[automatically_derived]
         impl #gen_imp  AsRef<#model_name #gen_ty> for #model_name #gen_ty #gen_wher {
             fn as_ref(&self) -> &#model_name {
@@ -140,27 +194,13 @@ pub fn process_struct(
     // let _map_name = format_ident!("{}", map_name);
     // let _kind_name = format_ident!("{}", kind_name);
 
-    let _names = fields.iter().map(|e| &e.name);
-
     let serialized_object_name = format_ident!("serialized");
 
     let modifiers: Vec<TokenStream> = fields
         .iter()
         .map(|f| {
-            let name = &f.name;
-            let data = &Ident::new("item", name.span());
-            let original_type = &f.original_type;
-            let deser_code = f.config.deserialization_code(
-                original_type,
-                data,
-                Some(quote!(#err::DeserializationErrorStackItem::Field(stringify!(#name)))),
-            )?;
-            Result::<TokenStream, MacroError>::Ok(quote_spanned! { original_type.span()=>
-                #name: {
-                    let #data = #serialized_object_name.#name;
-                    #deser_code
-                },
-            })
+            let member = &f.member;
+            f.initializer(quote!(#serialized_object_name.#member))
         })
         .try_collect()?;
 
@@ -188,15 +228,19 @@ pub fn process_struct(
     let where_condition =
         edit_where_clause(gen_wher, field_where_conditions, config.where_clauses());
 
+    let construct = config.validated(quote! {
+        #model_name {
+            #(#modifiers)*
+        }
+    });
+
     let deserialization_impl = quote! {
         #[automatically_derived]
         impl #gen_imp #ser::DeserializeModel<#model_name, Registry> for #serialized_name #gen_ty #where_condition {
             fn deserialize(self, registry: &mut Registry) -> Result<#model_name, #err::DeserializationError<Registry>> {
                 let #serialized_object_name = self;
 
-                Ok(#model_name {
-                    #(#modifiers)*
-                })
+                #construct
             }
         }
     };
@@ -204,8 +248,207 @@ pub fn process_struct(
     let all_together = quote! {
         #serialized_struct
 
+        #versions
+
         #deserialization_impl
     };
 
     Ok(all_together)
 }
+
+/// Processes struct fields, collecting helper functions for the schema
+/// defaults into `default_fns`
+///
+/// `schema_attrs` enables field-level `schemars` attributes, which are
+/// skipped for generic models and models without schema
+///
+/// `owner_name` is used for naming the default helper functions
+pub(super) fn collect_fields<'a>(
+    owner_name: &Ident,
+    struct_fields: &'a Fields<SpannedValue<FieldAttributeInput>>,
+    schema_attrs: bool,
+    default_fns: &mut Vec<TokenStream>,
+) -> Result<Vec<FieldData<'a>>, MacroError> {
+    let err = MOD_ERRORS.deref();
+    let mut fields = Vec::new();
+
+    for (index, field) in struct_fields.fields.iter().enumerate() {
+        let ty = &field.ty;
+        let (member, context) = match &field.ident {
+            Some(name) => (
+                Member::Named(name.clone()),
+                quote!(#err::DeserializationErrorStackItem::Field(stringify!(#name))),
+            ),
+            None => {
+                if field.config.default.is_some() {
+                    bail!(
+                        field.span(),
+                        "`default` attribute can only be used on named fields"
+                    )
+                }
+                let index_member = Member::Unnamed(syn::Index::from(index));
+                (
+                    index_member,
+                    quote!(#err::DeserializationErrorStackItem::Index(#index)),
+                )
+            }
+        };
+        let name = &member;
+
+        let serialized_type = field.config.config.serialized_ty(ty)?;
+        let fallthrough_attrs = fallthrough(&field.attrs);
+        let field_prefix = match &member {
+            Member::Named(name) => Some(quote!(#name:)),
+            Member::Unnamed(_) => None,
+        };
+        // References are annotated with the item kind, so tooling can
+        // resolve them without a loaded registry
+        let reference_schema = field
+            .config
+            .config
+            .referenced_item(ty)
+            .filter(|_| schema_attrs)
+            .map(|item| {
+                let ser = MOD_SERIALIZATION.deref();
+                // Optional fields keep `Option` as the outer schema type, so
+                // schemars doesn't mark them as required
+                let inner = match &field.config.default {
+                    None => field.config.config.serialized_option_inner(ty),
+                    Some(_) => Some(serialized_type.clone().into_owned()),
+                };
+                let with = match inner {
+                    None => quote!(#ser::schema::ReferenceSchema<#serialized_type, #item>),
+                    Some(inner) => quote!(Option<#ser::schema::ReferenceSchema<#inner, #item>>),
+                }
+                .to_string();
+                quote!(#[schemars(with = #with)])
+            });
+        let definition = match &field.config.default {
+            None => quote_spanned!(name.span()=>
+                #(#fallthrough_attrs)*
+                #reference_schema
+                #field_prefix #serialized_type
+            ),
+            Some(default) => {
+                // Defaults are exposed in the schema via a helper function,
+                // generic models are skipped since the helper can't name
+                // their serialized type. `#[model(default)]` fields only get
+                // the schema default if their serialized type has one
+                let schema_default = schema_attrs.then(|| {
+                    let ser = MOD_SERIALIZATION.deref();
+                    let func = format_ident!(
+                        "__{}_default_{}",
+                        owner_name,
+                        field
+                            .ident
+                            .as_ref()
+                            .expect("Defaults are only allowed on named fields")
+                    );
+                    let func_name = func.to_string();
+                    let value = match default {
+                        FieldDefault::Inherit => quote! {
+                            #[allow(unused_imports)]
+                            use #ser::schema::{ProbeDefault as _, ProbeNoDefault as _};
+                            (&#ser::schema::DefaultProbe::<#serialized_type>::new()).schema_default()
+                        },
+                        FieldDefault::Explicit(default) => {
+                            quote!(Some(::core::convert::Into::into(#default)))
+                        }
+                    };
+                    default_fns.push(quote_spanned! {name.span()=>
+                        #[allow(non_snake_case)]
+                        #[doc(hidden)]
+                        fn #func() -> Option<#serialized_type> {
+                            #value
+                        }
+                    });
+                    quote!(#[schemars(default = #func_name)])
+                });
+                // Present fields are always `Some`, so an explicit `null` of
+                // the `Option` field isn't replaced with the default
+                let ser = MOD_SERIALIZATION.deref();
+                let present = quote!(#ser::helpers::deserialize_present).to_string();
+                quote_spanned!(name.span()=>
+                    #(#fallthrough_attrs)*
+                    #[serde(default, deserialize_with = #present, skip_serializing_if = "Option::is_none")]
+                    #schema_default
+                    #reference_schema
+                    #field_prefix Option<#serialized_type>
+                )
+            }
+        };
+
+        let field_data = FieldData {
+            member,
+            context,
+            definition,
+            original_type: ty,
+            serialized_type,
+            default: field.config.default.as_ref(),
+            config: &field.config.config,
+        };
+
+        fields.push(field_data)
+    }
+
+    Ok(fields)
+}
+
+impl FieldData<'_> {
+    /// Type of the field in the serialized model, wrapped into `Option` for
+    /// fields with defaults
+    pub(super) fn field_type(&self) -> TokenStream {
+        let ty = &self.serialized_type;
+        match self.default {
+            None => quote!(#ty),
+            Some(_) => quote!(Option<#ty>),
+        }
+    }
+
+    /// Model field initializer, with `source` being the expression holding
+    /// the serialized field value
+    pub(super) fn initializer(&self, source: TokenStream) -> Result<TokenStream, MacroError> {
+        let name = &self.member;
+        let data = &Ident::new("item", name.span());
+        let original_type = &self.original_type;
+        let deser_code = self.config.deserialization_code(
+            original_type,
+            data,
+            Some(self.context.clone()),
+            None,
+        )?;
+        let value = match self.default {
+            None => quote! {
+                let #data = #source;
+                #deser_code
+            },
+            Some(FieldDefault::Inherit) => {
+                // The default value skips deserialization, but still goes
+                // through the validators
+                let target_type = self.config.from.as_ref().unwrap_or(original_type);
+                let default_code = self.config.deserialization_code(
+                    original_type,
+                    data,
+                    Some(self.context.clone()),
+                    Some(quote!(<#target_type as Default>::default())),
+                )?;
+                quote! {
+                    match #source {
+                        Some(#data) => #deser_code,
+                        None => #default_code,
+                    }
+                }
+            }
+            Some(FieldDefault::Explicit(default)) => quote! {
+                let #data = #source
+                    .unwrap_or_else(|| ::core::convert::Into::into(#default));
+                #deser_code
+            },
+        };
+        Ok(quote_spanned! { original_type.span()=>
+            #name: {
+                #value
+            },
+        })
+    }
+}
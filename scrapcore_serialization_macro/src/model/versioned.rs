@@ -0,0 +1,183 @@
+use crate::error::{bail, MacroError};
+use crate::model::attrs::ModelAttributeConfig;
+use crate::{MOD_ERRORS, MOD_REGISTRY, MOD_SERIALIZATION};
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use std::ops::Deref;
+use syn::Ident;
+
+/// Name of the untagged enum accepting both versioned and unversioned data
+pub(super) fn versioned_name(serialized_name: &Ident) -> Ident {
+    format_ident!("{}Versioned", serialized_name)
+}
+
+/// Generates the union of all serialized versions of the model, along with
+/// the upgrade chain
+///
+/// Must only be called for models with the `version` attribute
+pub(super) fn process_versions(
+    model_name: &Ident,
+    serialized_name: &Ident,
+    generics: &syn::Generics,
+    config: &ModelAttributeConfig,
+) -> Result<TokenStream, MacroError> {
+    let ser = MOD_SERIALIZATION.deref();
+    let reg = MOD_REGISTRY.deref();
+    let err = MOD_ERRORS.deref();
+
+    let version = config
+        .version
+        .as_ref()
+        .expect("Should only be called for versioned models");
+
+    if !generics.params.is_empty() {
+        bail!(version.span(), "Generic models can not be versioned")
+    }
+
+    let latest = **version;
+    if (latest as usize) <= config.upgrade_from.len() {
+        bail!(
+            version.span(),
+            "Model version must be greater than the number of `upgrade_from` shapes, as versions start from 1"
+        )
+    }
+
+    let versioned_name = versioned_name(serialized_name);
+    let versions_name = format_ident!("{}Versions", serialized_name);
+
+    let versions = (0..=config.upgrade_from.len() as u32)
+        .map(|i| latest - i)
+        .collect::<Vec<_>>();
+    let tags = versions.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    let numbers = versions
+        .iter()
+        .map(|v| Literal::u32_unsuffixed(*v))
+        .collect::<Vec<_>>();
+    let variants = versions
+        .iter()
+        .map(|v| format_ident!("V{}", v))
+        .collect::<Vec<_>>();
+    let types = std::iter::once(quote!(#serialized_name))
+        .chain(config.upgrade_from.iter().map(|ty| quote!(#ty)))
+        .collect::<Vec<_>>();
+
+    // Each historical shape is converted into all the newer shapes one by one
+    let upgrades = (0..types.len()).map(|i| {
+        types[..i].iter().rev().fold(
+            quote!(item),
+            |item, next| quote!(<#next as ::core::convert::From<_>>::from(#item)),
+        )
+    });
+
+    let schema_derive = config.schema_derive();
+    let latest_variant = &variants[0];
+
+    // Historical shapes are user types without strategies, so only the
+    // latest version is generated
+    let arbitrary = config.arbitrary_impl(&versioned_name, generics, || {
+        Ok(quote! {
+            #ser::arbitrary::Strategy::boxed(#ser::arbitrary::Strategy::prop_map(
+                <#serialized_name as #ser::arbitrary::ArbitrarySerialized>::arbitrary_serialized(pool),
+                Self::from,
+            ))
+        })
+    })?;
+
+    // Deserialization is written by hand, since serde tagged enums don't
+    // accept integer tags, and untagged ones fall through to the
+    // unversioned shape on any error
+    Ok(quote! {
+        #[derive(Debug, Clone, serde::Serialize)]
+        #schema_derive
+        #[serde(tag = "version")]
+        pub enum #versions_name {
+            #(
+                #[serde(rename = #tags)]
+                #variants(#types),
+            )*
+        }
+
+        #[derive(Debug, Clone, serde::Serialize)]
+        #schema_derive
+        #[serde(untagged)]
+        pub enum #versioned_name {
+            Versioned(#versions_name),
+            Unversioned(#serialized_name),
+        }
+
+        #[automatically_derived]
+        impl #versions_name {
+            /// Latest version of the serialized model
+            pub const LATEST: u32 = #latest;
+            /// All known versions of the serialized model, newest first
+            pub const VERSIONS: &'static [u32] = &[#(#numbers),*];
+
+            /// Deserializes the buffered data of the given version
+            fn from_data<E: serde::de::Error>(version: u32, data: &#ser::versioned::Value) -> Result<Self, E> {
+                match version {
+                    #(#numbers => #ser::versioned::from_data(data).map(Self::#variants),)*
+                    _ => Err(#ser::versioned::unknown_version(version, Self::VERSIONS)),
+                }
+            }
+
+            /// Upgrades the data to the latest serialized version
+            pub fn upgrade(self) -> #serialized_name {
+                match self {
+                    #(Self::#variants(item) => #upgrades,)*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #versioned_name {
+            /// Upgrades the data to the latest serialized version
+            pub fn upgrade(self) -> #serialized_name {
+                match self {
+                    Self::Versioned(item) => item.upgrade(),
+                    Self::Unversioned(item) => item,
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl<'de> serde::Deserialize<'de> for #versions_name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                match #ser::versioned::split_version(deserializer)? {
+                    (Some(version), data) => Self::from_data(version, &data),
+                    (None, _) => Err(serde::de::Error::missing_field(#ser::versioned::VERSION_FIELD)),
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl<'de> serde::Deserialize<'de> for #versioned_name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                // Only data without a version is read as the latest shape
+                match #ser::versioned::split_version(deserializer)? {
+                    (Some(version), data) => #versions_name::from_data(version, &data).map(Self::Versioned),
+                    (None, data) => #ser::versioned::from_data(&data).map(Self::Unversioned),
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl From<#serialized_name> for #versioned_name {
+            fn from(value: #serialized_name) -> Self {
+                Self::Versioned(#versions_name::#latest_variant(value))
+            }
+        }
+
+        #[automatically_derived]
+        impl<Registry> #ser::DeserializeModel<#model_name, Registry> for #versioned_name
+        where
+            #serialized_name: #ser::DeserializeModel<#model_name, Registry>,
+            Registry: #reg::PartialRegistry,
+        {
+            fn deserialize(self, registry: &mut Registry) -> Result<#model_name, #err::DeserializationError<Registry>> {
+                #ser::DeserializeModel::<#model_name, Registry>::deserialize(self.upgrade(), registry)
+            }
+        }
+
+        #arbitrary
+    })
+}
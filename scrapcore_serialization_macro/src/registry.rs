@@ -7,7 +7,7 @@ use syn::{parse_macro_input, DeriveInput, Type, Visibility};
 
 use crate::error::{tokens, MacroError};
 use crate::registry::parser::parse_struct_defs;
-use crate::{MOD_ERRORS, MOD_REGISTRY};
+use crate::{MOD_ERRORS, MOD_REGISTRY, MOD_SERIALIZATION, SERIALIZATION_CRATE};
 
 mod parser;
 
@@ -44,6 +44,13 @@ struct AssetKind {
     ty: Type,
 }
 
+/// Registry item used for resolving expression variables
+#[derive(Debug)]
+enum VariablesSource {
+    Collection(Type),
+    Singleton(Type),
+}
+
 #[derive(Debug, Default)]
 struct ModelSet(Vec<ModelKind>);
 
@@ -78,12 +85,19 @@ struct RegistryDefinitions {
     partial_registry_name: Ident,
     visibility: Visibility,
     schema: bool,
+    /// Whether to generate `proptest` strategies for the serialized model
+    arbitrary: bool,
+    /// Whether to implement `RemapIds` for the registry
+    remap_ids: bool,
+    /// Serde tag field name of the serialized registry items
+    tag: String,
 
     error: Type,
 
     singletons: ModelSet,
     collections: ModelSet,
     assets: Vec<AssetKind>,
+    variables: Option<VariablesSource>,
 }
 
 pub(crate) fn registry_impl_inner(
@@ -102,6 +116,11 @@ pub(crate) fn registry_impl_inner(
     let finalize = definitions.partial_finalize();
     let insert_impl = definitions.insert_impl();
     let item_ids = definitions.item_ids();
+    let schema_impl = definitions.schema_impl();
+    let loadable_impl = definitions.loadable_impl();
+    let items_impl = definitions.items_impl();
+    let arbitrary_impl = definitions.arbitrary_impl();
+    let remap_ids_impl = definitions.remap_ids_impl();
 
     Ok(quote! {
         #model
@@ -113,6 +132,11 @@ pub(crate) fn registry_impl_inner(
         #impls
         #finalize
         #insert_impl
+        #schema_impl
+        #loadable_impl
+        #items_impl
+        #arbitrary_impl
+        #remap_ids_impl
 
         pub mod id {
             use super::*;
@@ -149,10 +173,11 @@ impl RegistryDefinitions {
         let serialized_model_name = &self.serialized_model_name;
         let schema_derive = self.schema.then(|| quote!(#[derive(schemars::JsonSchema)]));
         let model_name_str = self.model_name.to_string();
+        let tag = &self.tag;
         let model_enum = quote! {
             #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
             #schema_derive
-            #[serde(tag = "type")]
+            #[serde(tag = #tag)]
             #[serde(rename = #model_name_str)]
             #visibility enum #serialized_model_name {
                 #(#singletons,)*
@@ -163,6 +188,59 @@ impl RegistryDefinitions {
         model_enum
     }
 
+    /// `ArbitrarySerialized` implementation for the serialized model, with
+    /// collection entry keys taken from the key pool
+    fn arbitrary_impl(&self) -> Option<TokenStream> {
+        if !self.arbitrary {
+            return None;
+        }
+        let ser = MOD_SERIALIZATION.deref();
+        let singletons = self.singletons.iter().map(
+            |ModelKind {
+                 variant_name,
+                 ty_serialized,
+                 span,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    #ser::arbitrary::Strategy::boxed(#ser::arbitrary::Strategy::prop_map(
+                        <#ty_serialized as #ser::arbitrary::ArbitrarySerialized>::arbitrary_serialized(pool),
+                        Self::#variant_name,
+                    ))
+                }
+            },
+        );
+        let collections = self.collections.iter().map(
+            |ModelKind {
+                 variant_name,
+                 ty,
+                 ty_serialized,
+                 span,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    #ser::arbitrary::Strategy::boxed(#ser::arbitrary::Strategy::prop_map(
+                        #ser::arbitrary::registry_entry::<#ty, #ty_serialized>(pool),
+                        Self::#variant_name,
+                    ))
+                }
+            },
+        );
+        let serialized_model_name = &self.serialized_model_name;
+
+        Some(quote! {
+            #[automatically_derived]
+            impl #ser::arbitrary::ArbitrarySerialized for #serialized_model_name {
+                fn arbitrary_serialized(pool: &#ser::arbitrary::KeyPool) -> #ser::arbitrary::BoxedStrategy<Self> {
+                    #ser::arbitrary::Strategy::boxed(#ser::arbitrary::Union::new([
+                        #(#singletons,)*
+                        #(#collections,)*
+                    ]))
+                }
+            }
+        })
+    }
+
     /// Definitions for the model enum
     fn model(&self) -> TokenStream {
         let singletons = self.singletons.variants();
@@ -365,6 +443,7 @@ impl RegistryDefinitions {
             collections,
             assets,
             visibility,
+            kind_name,
             ..
         } = self;
         let reg = MOD_REGISTRY.deref();
@@ -410,6 +489,7 @@ impl RegistryDefinitions {
             #[derive(Debug, Default)]
             #visibility struct #partial_registry_name {
                 poisoned__: bool,
+                current_item__: Option<#reg::ItemContext<#kind_name>>,
                 #(#singletons)*
                 #(#collections)*
                 #(#assets)*
@@ -428,10 +508,12 @@ impl RegistryDefinitions {
             kind_name,
             assets_kind_name,
             error,
+            variables,
             ..
         } = self;
 
         let reg = MOD_REGISTRY.deref();
+        let err = MOD_ERRORS.deref();
         let singletons = singletons.iter().map(
             |ModelKind {
                  span,
@@ -535,6 +617,22 @@ impl RegistryDefinitions {
             },
         );
 
+        let get_variable = variables.as_ref().map(|source| {
+            let lookup = match source {
+                VariablesSource::Collection(ty) => {
+                    quote!(#reg::variables::collection_variable::<#ty, Self>(self, name))
+                }
+                VariablesSource::Singleton(ty) => {
+                    quote!(#reg::variables::singleton_variable::<#ty, Self>(self, name))
+                }
+            };
+            quote! {
+                fn get_variable(&mut self, name: &str) -> Result<f64, #err::DeserializationError<Self>> {
+                    #lookup
+                }
+            }
+        });
+
         quote! {
             #(#singletons)*
             #(#collections_impls)*
@@ -557,7 +655,7 @@ impl RegistryDefinitions {
             impl #reg::PartialRegistry for #partial_registry_name {
                 type Registry = #registry_name;
 
-                fn reserve_ids(&mut self, registry: &Self::Registry) -> Result<(), DeserializationError<Self>> {
+                fn reserve_ids(&mut self, registry: &Self::Registry) -> Result<(), #err::DeserializationError<Self>> {
                     #(#reservations)*
 
                     Ok(())
@@ -570,6 +668,19 @@ impl RegistryDefinitions {
                 fn is_poisoned(&self) -> bool {
                     self.poisoned__
                 }
+
+                fn current_item(&self) -> Option<#reg::ItemContext<#kind_name>> {
+                    self.current_item__.clone()
+                }
+
+                fn set_current_item(
+                    &mut self,
+                    item: Option<#reg::ItemContext<#kind_name>>,
+                ) -> Option<#reg::ItemContext<#kind_name>> {
+                    std::mem::replace(&mut self.current_item__, item)
+                }
+
+                #get_variable
             }
         }
     }
@@ -698,6 +809,298 @@ impl RegistryDefinitions {
         }
     }
 
+    /// Methods for collecting known item keys and asset names, and
+    /// generating JSON schema restricted to them
+    fn schema_impl(&self) -> TokenStream {
+        let Self {
+            registry_name,
+            partial_registry_name,
+            serialized_model_name,
+            collections,
+            assets,
+            schema,
+            ..
+        } = self;
+
+        let ser = MOD_SERIALIZATION.deref();
+
+        let items = collections.iter().map(
+            |ModelKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ty,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    references.add_items::<#ty>(stringify!(#variant_name), self.#field_name.keys().cloned());
+                }
+            },
+        );
+        let assets = assets.iter().map(
+            |AssetKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ty,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    references.add_assets::<#ty>(stringify!(#variant_name), self.#field_name.keys().cloned());
+                }
+            },
+        );
+        let collect = quote! {
+            /// Item keys and asset names currently present in the registry
+            pub fn schema_references(&self) -> #ser::schema::SchemaReferences {
+                let mut references = #ser::schema::SchemaReferences::default();
+                #(#items)*
+                #(#assets)*
+                references
+            }
+        };
+        let items_schema = schema.then(|| {
+            quote! {
+                /// JSON schema of the serialized items, with item and asset
+                /// references restricted to the ones present in the registry
+                pub fn items_schema(&self) -> schemars::schema::RootSchema {
+                    self.schema_references().schema_for::<#serialized_model_name>()
+                }
+            }
+        });
+
+        quote! {
+            impl #registry_name {
+                #collect
+                #items_schema
+            }
+
+            impl #partial_registry_name {
+                #collect
+                #items_schema
+            }
+        }
+    }
+
+    /// Implementation of `LoadableRegistry` for the partial registry
+    fn loadable_impl(&self) -> TokenStream {
+        let Self {
+            registry_name,
+            partial_registry_name,
+            serialized_model_name,
+            kind_name,
+            assets_kind_name,
+            singletons,
+            collections,
+            assets,
+            ..
+        } = self;
+
+        let krate = SERIALIZATION_CRATE.deref();
+        let reg = MOD_REGISTRY.deref();
+        let ser = MOD_SERIALIZATION.deref();
+        let err = MOD_ERRORS.deref();
+
+        let singleton_keys = singletons.iter().map(
+            |ModelKind {
+                 span, variant_name, ..
+             }| {
+                quote_spanned! {*span=>
+                    #serialized_model_name::#variant_name(_) => (#kind_name::#variant_name, None)
+                }
+            },
+        );
+        let collection_keys = collections.iter().map(|ModelKind { span, variant_name, .. }| {
+            quote_spanned! {*span=>
+                #serialized_model_name::#variant_name(item) => (#kind_name::#variant_name, Some(item.id.clone()))
+            }
+        });
+        let singleton_counts = singletons.iter().map(
+            |ModelKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    (#kind_name::#variant_name, self.#field_name.iter().count())
+                }
+            },
+        );
+        let collection_counts = collections.iter().map(
+            |ModelKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    (#kind_name::#variant_name, self.#field_name.keys().count())
+                }
+            },
+        );
+        let asset_counts = assets.iter().map(
+            |AssetKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    (#assets_kind_name::#variant_name, self.#field_name.len())
+                }
+            },
+        );
+
+        quote! {
+            #[automatically_derived]
+            impl #reg::LoadableRegistry for #partial_registry_name {
+                type Item = #serialized_model_name;
+
+                fn insert(&mut self, path: impl Into<#reg::path_identifier::PathIdentifier>, item: Self::Item) -> Result<(), #err::DeserializationError<Self>> {
+                    #partial_registry_name::insert(self, path, item)
+                }
+
+                fn into_registry(self) -> Result<#registry_name, #err::DeserializationError<Self>> {
+                    #partial_registry_name::into_registry(self)
+                }
+
+                fn schema_references(&self) -> #ser::schema::SchemaReferences {
+                    #partial_registry_name::schema_references(self)
+                }
+
+                fn item_key(item: &Self::Item) -> (#kind_name, Option<#krate::ItemId>) {
+                    match item {
+                        #(#singleton_keys,)*
+                        #(#collection_keys,)*
+                    }
+                }
+
+                fn item_counts(&self) -> Vec<(#kind_name, usize)> {
+                    vec![#(#singleton_counts,)* #(#collection_counts,)*]
+                }
+
+                fn asset_counts(&self) -> Vec<(#assets_kind_name, usize)> {
+                    vec![#(#asset_counts,)*]
+                }
+            }
+        }
+    }
+
+    /// Implementation of `RegistryItems` for the registry
+    fn items_impl(&self) -> TokenStream {
+        let Self {
+            registry_name,
+            kind_name,
+            singletons,
+            collections,
+            ..
+        } = self;
+
+        let reg = MOD_REGISTRY.deref();
+        let krate = SERIALIZATION_CRATE.deref();
+
+        let visit_singletons = singletons.iter().map(
+            |ModelKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    visit(#kind_name::#variant_name, None, &self.#field_name);
+                }
+            },
+        );
+        let visit_collections = collections.iter().map(
+            |ModelKind {
+                 span,
+                 field_name,
+                 variant_name,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    for (key, id) in self.#field_name.keys_ids() {
+                        visit(#kind_name::#variant_name, Some(key), &self.#field_name[id].data);
+                    }
+                }
+            },
+        );
+        let keys = collections.iter().map(
+            |ModelKind {
+                 span,
+                 field_name,
+                 ty,
+                 ..
+             }| {
+                quote_spanned! {*span=>
+                    if value_type == std::any::type_name::<#reg::entry::RegistryEntry<#ty>>() {
+                        return self.#field_name.untyped_to_key(id);
+                    }
+                }
+            },
+        );
+
+        quote! {
+            #[automatically_derived]
+            impl #reg::RegistryItems for #registry_name {
+                fn visit_items(&self, visit: &mut #reg::ItemVisitor<'_, #kind_name>) {
+                    #(#visit_singletons)*
+                    #(#visit_collections)*
+                }
+
+                fn id_to_key(&self, value_type: &str, id: #krate::slabmap::SlabMapUntypedId) -> Option<&#krate::ItemId> {
+                    #(#keys)*
+                    None
+                }
+            }
+        }
+    }
+
+    /// `RemapIds` implementation for the registry, remapping ids held by all
+    /// singletons and collection items
+    fn remap_ids_impl(&self) -> Option<TokenStream> {
+        if !self.remap_ids {
+            return None;
+        }
+        let reg = MOD_REGISTRY.deref();
+        let singletons = self.singletons.iter().map(
+            |ModelKind {
+                 span, field_name, ..
+             }| {
+                quote_spanned! {*span=>
+                    #reg::remap::RemapIds::remap_ids(&mut self.#field_name, remap)?;
+                }
+            },
+        );
+        let collections = self.collections.iter().map(
+            |ModelKind {
+                 span, field_name, ..
+             }| {
+                quote_spanned! {*span=>
+                    for entry in self.#field_name.values_mut() {
+                        #reg::remap::RemapIds::remap_ids(entry, remap)?;
+                    }
+                }
+            },
+        );
+        let registry_name = &self.registry_name;
+
+        Some(quote! {
+            #[automatically_derived]
+            impl #reg::remap::RemapIds for #registry_name {
+                fn remap_ids(
+                    &mut self,
+                    remap: &#reg::remap::RegistryRemap,
+                ) -> Result<(), #reg::remap::DanglingIdError> {
+                    #(#singletons)*
+                    #(#collections)*
+                    Ok(())
+                }
+            }
+        })
+    }
+
     /// Type aliases for item IDs
     fn item_ids(&self) -> TokenStream {
         let Self {
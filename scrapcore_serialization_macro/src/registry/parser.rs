@@ -8,13 +8,19 @@ use syn::spanned::Spanned;
 use syn::{DeriveInput, Generics, Type, Visibility};
 
 use crate::error::{bail, MacroError};
-use crate::registry::{AssetKind, ModelKind, RegistryDefinitions};
+use crate::registry::{AssetKind, ModelKind, RegistryDefinitions, VariablesSource};
 use crate::serialized_of;
 
 #[derive(Debug, FromMeta)]
 struct RegistryAttributeInput {
     /// Whether to skip emitting `schemars` derives for the model
     no_schema: Flag,
+    /// Whether to implement `ArbitrarySerialized` for the serialized model,
+    /// requires all models to be marked with `#[model(arbitrary)]`
+    arbitrary: Flag,
+    /// Whether to implement `RemapIds` for the registry, requires all models
+    /// to be marked with `#[model(remap_ids)]`
+    remap_ids: Flag,
     /// Overrides the name of the registry items. Defaults to the struct name
     /// with "Item" appended
     item_name: Option<Ident>,
@@ -38,6 +44,9 @@ struct RegistryAttributeInput {
     visibility: Option<Visibility>,
     /// Custom registry error type
     error: Type,
+    /// Name of the field holding the item type in the serialized registry
+    /// items. Defaults to "type"
+    tag: Option<String>,
 }
 
 #[derive(Debug, FromVariant)]
@@ -47,6 +56,9 @@ struct ModelAttributeInput {
     collection: Flag,
     singleton: Flag,
 
+    /// Marks the collection or singleton as a storage of expression variables
+    variables: Flag,
+
     /// Name of the ID type for this model (only applies to collections)
     id_name: Option<Ident>,
 
@@ -122,9 +134,13 @@ pub(super) fn parse_struct_defs(
         model_name: registry_item_name,
         error: input.error,
         schema: !input.no_schema.is_present(),
+        arbitrary: input.arbitrary.is_present(),
+        remap_ids: input.remap_ids.is_present(),
+        tag: input.tag.unwrap_or_else(|| "type".to_string()),
         singletons: Default::default(),
         collections: Default::default(),
         assets: Default::default(),
+        variables: None,
         visibility,
     };
 
@@ -152,6 +168,21 @@ pub(super) fn parse_struct_defs(
             }
         }
 
+        if variant.variables.is_present() {
+            if variant.asset.is_present() {
+                bail!(
+                    variant.variables.span(),
+                    "`variables` attribute can only be used with `collection` or `singleton` markers"
+                )
+            }
+            if registry.variables.is_some() {
+                bail!(
+                    variant.variables.span(),
+                    "Only one registry item can be marked as `variables`"
+                )
+            }
+        }
+
         if variant.asset.is_present() {
             if !variant.fields.is_newtype() {
                 unreachable!("Should only have newtype enums")
@@ -199,6 +230,14 @@ pub(super) fn parse_struct_defs(
                 ty,
                 ty_serialized,
             };
+            if variant.variables.is_present() {
+                let ty = model.ty.clone();
+                registry.variables = Some(if variant.collection.is_present() {
+                    VariablesSource::Collection(ty)
+                } else {
+                    VariablesSource::Singleton(ty)
+                });
+            }
             if variant.collection.is_present() {
                 registry.collections.0.push(model);
             } else if variant.singleton.is_present() {
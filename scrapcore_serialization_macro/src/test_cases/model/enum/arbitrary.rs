@@ -0,0 +1,10 @@
+#[derive(Debug, DatabaseModel)]
+#[model(arbitrary)]
+enum Plot {
+    Empty,
+    House(House),
+    Park {
+        #[model(default)]
+        trees: u32,
+    },
+}
@@ -0,0 +1,13 @@
+#[derive(Debug, DatabaseModel)]
+#[model(remap_ids)]
+enum Plot {
+    Empty,
+    Owned(PersonId),
+    #[model(raw)]
+    Painted(Color),
+    Park {
+        keeper: Option<InlineOrId<Person>>,
+        #[model(raw)]
+        color: Color,
+    },
+}
@@ -0,0 +1,10 @@
+#[derive(Debug, DatabaseModel)]
+enum Plot {
+    Empty,
+    House {
+        #[model(id)]
+        residents: Vec<PersonId>,
+        #[model(min = 1, default = 1)]
+        floor_count: u8,
+    },
+}
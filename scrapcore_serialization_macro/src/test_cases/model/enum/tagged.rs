@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(tag = "kind", content = "data", rename_all = "snake_case")]
+enum Test {
+    Empty,
+    SomeValue(u32),
+}
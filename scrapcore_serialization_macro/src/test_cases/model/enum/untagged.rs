@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(untagged)]
+enum Test {
+    Number(u32),
+    Text(String),
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(untagged, tag = "kind")]
+enum Test {
+    Number(u32),
+    Text(String),
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(upgrade_from = "TestV1")]
+enum Test {
+    Empty,
+    Value(u32),
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(validate = checks::validate_test)]
+enum Test {
+    Empty,
+    Value(u32),
+}
@@ -0,0 +1,9 @@
+#[derive(Debug, DatabaseModel)]
+#[model(arbitrary, version = 2, upgrade_from = "TestV1")]
+struct Test {
+    name: String,
+    #[model(id)]
+    friend: Option<PersonId>,
+    #[model(default = 18)]
+    age: u32,
+}
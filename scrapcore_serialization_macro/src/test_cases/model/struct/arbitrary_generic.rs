@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+#[model(arbitrary)]
+struct Test<T> {
+    value: T,
+}
@@ -0,0 +1,11 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    a: u32,
+    #[model(min = 0.5)]
+    b: Option<f64>,
+    #[model(raw)]
+    c: i64,
+    #[model(from = "u8")]
+    d: u16,
+    e: usize,
+}
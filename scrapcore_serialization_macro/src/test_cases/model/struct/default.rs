@@ -0,0 +1,11 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    #[model(default)]
+    a: u32,
+    #[model(default = 5, min = 1)]
+    b: u32,
+    #[model(id, default = "core:alex")]
+    c: PersonId,
+    #[model(default, len_min = 1)]
+    d: Vec<String>,
+}
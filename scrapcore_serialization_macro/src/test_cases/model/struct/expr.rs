@@ -0,0 +1,7 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    #[model(expr)]
+    a: u32,
+    #[model(expr, min = 0.5)]
+    b: f64,
+}
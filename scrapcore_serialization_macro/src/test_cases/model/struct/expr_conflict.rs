@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    #[model(expr, ty = "f64")]
+    a: u32
+}
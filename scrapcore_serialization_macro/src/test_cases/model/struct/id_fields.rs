@@ -0,0 +1,9 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    #[model(id)]
+    mom: Option<PersonId>,
+    #[model(id_of = "Person")]
+    friends: Vec<PersonId>,
+    #[model(id, default = "core:alex")]
+    mayor: PersonId,
+}
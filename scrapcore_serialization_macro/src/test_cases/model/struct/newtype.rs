@@ -0,0 +1,2 @@
+#[derive(Debug, DatabaseModel)]
+struct Health(#[model(min = 0.0)] f32);
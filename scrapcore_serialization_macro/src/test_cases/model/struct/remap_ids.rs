@@ -0,0 +1,9 @@
+#[derive(Debug, DatabaseModel)]
+#[model(remap_ids)]
+struct Owner {
+    #[model(id)]
+    person: PersonId,
+    friends: Vec<PersonId>,
+    #[model(raw)]
+    color: Color,
+}
@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+#[model(remap_ids)]
+struct Wrapper<T> {
+    value: T,
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(rename_all = "snake_case")]
+struct Test {
+    field_a: u32,
+    field_b: f64,
+}
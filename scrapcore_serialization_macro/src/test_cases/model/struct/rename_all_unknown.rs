@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+#[model(rename_all = "snakeCase")]
+struct Test {
+    field_a: u32,
+}
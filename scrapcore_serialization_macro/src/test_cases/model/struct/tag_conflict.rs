@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+#[model(tag = "kind")]
+struct Test {
+    field_a: u32,
+}
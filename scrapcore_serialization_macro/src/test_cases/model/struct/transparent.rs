@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(transparent)]
+struct Owner {
+    #[model(id)]
+    person: PersonId,
+}
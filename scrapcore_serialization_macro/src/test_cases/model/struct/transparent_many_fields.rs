@@ -0,0 +1,3 @@
+#[derive(Debug, DatabaseModel)]
+#[model(transparent)]
+struct Test(u32, u32);
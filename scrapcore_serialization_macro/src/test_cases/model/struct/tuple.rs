@@ -0,0 +1,2 @@
+#[derive(Debug, DatabaseModel)]
+struct Test(u32, #[model(min = 0.0)] f64);
@@ -0,0 +1,2 @@
+#[derive(Debug, DatabaseModel)]
+struct Marker;
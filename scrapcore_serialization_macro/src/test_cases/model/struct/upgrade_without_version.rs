@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+#[model(upgrade_from = "TestV1")]
+struct Test {
+    name: String,
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, DatabaseModel)]
+#[model(validate = validate_test)]
+struct Test {
+    min_damage: u32,
+    max_damage: u32,
+}
@@ -0,0 +1,13 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    #[model(non_empty, len_max = 32, regex = "^[a-z_]+$")]
+    a: String,
+    #[model(len_min = 1, len_max = 4)]
+    b: Vec<u32>,
+    #[model(finite, min = 0.0)]
+    c: f64,
+    #[model(one_of = ["north", "south"])]
+    d: String,
+    #[model(one_of = [1, 2, 4])]
+    e: Option<u8>,
+}
@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+struct Test {
+    #[model(regex = "^[a-z")]
+    a: String,
+}
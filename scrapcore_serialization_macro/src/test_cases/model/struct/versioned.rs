@@ -0,0 +1,7 @@
+#[derive(Debug, DatabaseModel)]
+#[model(version = 3, upgrade_from = "TestV2", upgrade_from = "TestV1")]
+struct Test {
+    name: String,
+    #[model(min = 0)]
+    age: u32,
+}
@@ -0,0 +1,5 @@
+#[derive(Debug, DatabaseModel)]
+#[model(version = 1, upgrade_from = "TestV0")]
+struct Test {
+    name: String,
+}
@@ -0,0 +1,7 @@
+#[registry(error = "ModelError", arbitrary)]
+pub enum Model {
+    #[model(collection)]
+    Test(A),
+    #[model(singleton)]
+    Settings(B),
+}
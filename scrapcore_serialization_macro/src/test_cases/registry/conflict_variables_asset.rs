@@ -0,0 +1,5 @@
+#[registry(error = "ModelError")]
+pub enum Model {
+    #[model(asset, variables)]
+    Test(A),
+}
@@ -0,0 +1,5 @@
+#[registry(error = "ModelError", tag = "kind")]
+pub enum Model {
+    #[model(collection)]
+    Test(A),
+}
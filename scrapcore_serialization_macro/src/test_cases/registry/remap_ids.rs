@@ -0,0 +1,7 @@
+#[registry(error = "ModelError", remap_ids)]
+pub enum Model {
+    #[model(collection)]
+    Test(A),
+    #[model(singleton)]
+    Settings(B),
+}
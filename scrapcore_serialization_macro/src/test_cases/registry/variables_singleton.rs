@@ -0,0 +1,7 @@
+#[registry(error = "ModelError")]
+pub enum Model {
+    #[model(collection)]
+    Test(A),
+    #[model(singleton, variables)]
+    Constants(B),
+}
@@ -0,0 +1,30 @@
+use crate::ModelError;
+use bevy_asset::{Handle, LoadedFolder};
+use scrapcore_serialization::derive::{registry, DatabaseModel};
+use scrapcore_serialization::serialization::strong::Strong;
+
+use id::VisitorId;
+
+#[derive(Debug, DatabaseModel)]
+pub struct Painting {
+    pub frame: Handle<LoadedFolder>,
+    pub strong_frame: Strong<Handle<LoadedFolder>>,
+    #[model(id)]
+    pub author: Option<VisitorId>,
+}
+
+/// Named like the city person, but registered under a different kind
+#[derive(Debug, DatabaseModel)]
+pub struct Person {
+    pub name: String,
+}
+
+#[registry(error = "ModelError")]
+pub enum Gallery {
+    #[model(collection)]
+    Painting(Painting),
+    #[model(collection)]
+    Visitor(Person),
+    #[model(asset)]
+    Frame(Handle<LoadedFolder>),
+}
@@ -1,18 +1,32 @@
+use schemars::JsonSchema;
 use scrapcore_serialization::derive::{registry, DatabaseModel};
+use scrapcore_serialization::registry::inline::InlineOrId;
+use scrapcore_serialization::registry::variables::ExprVariableValue;
+use scrapcore_serialization::registry::{ItemContext, PartialCollectionHolder};
 use scrapcore_serialization::serialization::error::{
     DeserializationError, DeserializationErrorKind,
 };
+use scrapcore_serialization::ItemId;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use thiserror::Error;
 use walkdir::WalkDir;
 
+pub mod gallery;
 #[cfg(test)]
 mod tests;
 
 use id::PersonId;
 
 #[derive(Debug, DatabaseModel)]
+#[model(
+    arbitrary,
+    remap_ids,
+    validate = validate_person,
+    validate_with_context = validate_own_parent
+)]
 pub struct Person {
+    #[model(non_empty, len_max = 64)]
     pub name: String,
     #[model(id)]
     pub mom: Option<PersonId>,
@@ -20,35 +34,146 @@ pub struct Person {
     pub dad: Option<PersonId>,
 }
 
+fn validate_person<Registry: PartialCollectionHolder<Person>>(
+    person: &Person,
+    _registry: &mut Registry,
+) -> Result<(), DeserializationError<Registry>> {
+    if person.mom.is_some() && person.mom == person.dad {
+        return Err(DeserializationErrorKind::ValidationFailed(
+            "mom and dad must be different people".to_string(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn validate_own_parent<Registry: PartialCollectionHolder<Person>>(
+    person: &Person,
+    item: Option<ItemContext<Registry::ItemKind>>,
+    registry: &mut Registry,
+) -> Result<(), DeserializationError<Registry>> {
+    let own_id = item.and_then(|item| item.id_of::<Person, _>(registry));
+    if own_id.is_some() && (person.mom == own_id || person.dad == own_id) {
+        return Err(DeserializationErrorKind::ValidationFailed(
+            "person can not be their own parent".to_string(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
 #[derive(Debug, DatabaseModel)]
+#[model(arbitrary, remap_ids)]
 pub struct House {
+    #[model(len_min = 1, len_max = 8)]
     pub residents: Vec<Person>,
 }
 
 #[derive(Debug, DatabaseModel)]
+#[model(arbitrary, remap_ids)]
 pub struct Theater {
+    #[model(regex = "^[A-Z]")]
     pub name: String,
+    #[model(expr)]
     pub seats: u32,
+    #[model(finite, min = 0.0)]
+    pub ticket_price: f32,
+    #[model(one_of = ["drama", "comedy", "opera"])]
+    pub genre: Option<String>,
 }
 
 #[derive(Debug, DatabaseModel)]
+#[model(arbitrary, remap_ids)]
 pub enum Plot {
     Empty,
     House(House),
     Theater(Theater),
+    Park {
+        #[model(non_empty)]
+        name: String,
+        #[model(default)]
+        trees: u32,
+        #[model(default)]
+        keeper: Option<InlineOrId<Person>>,
+    },
+}
+
+#[derive(Debug, DatabaseModel)]
+pub struct Health(#[model(min = 0.0)] pub f32);
+
+#[derive(Debug, DatabaseModel)]
+pub struct Landmark;
+
+#[derive(Debug, DatabaseModel)]
+pub struct Coordinates(pub i32, #[model(min = 0)] pub i32);
+
+#[derive(Debug, DatabaseModel)]
+#[model(transparent)]
+pub struct Owner {
+    #[model(id)]
+    pub person: PersonId,
 }
 
 #[derive(Debug, DatabaseModel)]
+#[model(rename_all = "snake_case")]
+pub struct Rain {
+    pub drops_per_hour: u32,
+}
+
+#[derive(Debug, DatabaseModel)]
+#[model(tag = "kind", rename_all = "snake_case")]
+pub enum Weather {
+    Sunny,
+    Rain(Rain),
+}
+
+#[derive(Debug, DatabaseModel)]
+#[model(arbitrary, remap_ids, version = 2, upgrade_from = "MayorV1")]
 pub struct Mayor {
     pub person: PersonId,
+    #[model(id, default = "test:alex")]
+    pub deputy: PersonId,
+    #[model(default)]
+    pub term: u32,
+}
+
+/// First version of the mayor data, before `deputy` and `term` fields were
+/// added
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MayorV1 {
+    pub person: ItemId,
+}
+
+impl From<MayorV1> for MayorSerialized {
+    fn from(value: MayorV1) -> Self {
+        MayorSerialized {
+            person: value.person.into(),
+            deputy: None,
+            term: None,
+        }
+    }
+}
+
+#[derive(Debug, DatabaseModel)]
+#[model(arbitrary, remap_ids)]
+pub struct Variable {
+    pub value: f64,
+}
+
+impl ExprVariableValue for Variable {
+    fn variable_value(&self) -> f64 {
+        self.value
+    }
 }
 
-#[registry(error = "ModelError")]
+#[registry(error = "ModelError", arbitrary, remap_ids)]
 pub enum City {
     #[model(collection)]
     Person(Person),
     #[model(collection)]
     Plot(Plot),
+    #[model(collection, variables)]
+    Variable(Variable),
     #[model(singleton)]
     Mayor(Mayor),
 }
@@ -56,7 +181,9 @@ pub enum City {
 #[derive(Debug, Clone, Error)]
 pub enum ModelError {}
 
-fn load_database(path: &Path) -> Result<CityRegistry, DeserializationError<PartialCityRegistry>> {
+pub fn load_database(
+    path: &Path,
+) -> Result<CityRegistry, DeserializationError<PartialCityRegistry>> {
     let mut registry = PartialCityRegistry::default();
     for entry in WalkDir::new(path).into_iter() {
         let entry = entry.unwrap();
@@ -1,9 +1,47 @@
-use crate::{load_database, CityItemSerialized};
+use crate::gallery::{GalleryItemSerialized, PartialGalleryRegistry};
+use crate::{
+    load_database, CityItemKind, CityItemSerialized, CityRegistry, Coordinates,
+    CoordinatesSerialized, Health, HealthSerialized, LandmarkSerialized, Owner, OwnerSerialized,
+    PartialCityRegistry, Person, Plot, PlotSerialized, Variable, Weather, WeatherSerialized,
+};
+use bevy_app::{App, Update};
+use bevy_asset::{AssetPlugin, Assets, LoadedFolder};
+use bevy_ecs::event::Events;
+use bevy_ecs::schedule::ExecutorKind;
+use bevy_tasks::{AsyncComputeTaskPool, ComputeTaskPool, IoTaskPool, TaskPool};
+use rayon::prelude::*;
+use scrapcore_serialization::bevy_plugin::{
+    LoadedRegistry, RegistryPlugin, RegistryReloaded, RegistrySource,
+};
+use scrapcore_serialization::cli::{run, CliFailure};
+use scrapcore_serialization::registry::inline::InlineOrId;
+use scrapcore_serialization::registry::insert::asset_insert;
+use scrapcore_serialization::registry::path_identifier::PathIdentifier;
+use scrapcore_serialization::registry::remap::{compact_collection, sort_collection};
+use scrapcore_serialization::registry::CollectionHolder;
+use scrapcore_serialization::serialization::arbitrary::{check_registry, KeyPool};
+use scrapcore_serialization::serialization::constant::ConstOr;
+use scrapcore_serialization::serialization::error::{
+    DeserializationError, DeserializationErrorKind, DeserializationErrorStackItem,
+};
+use scrapcore_serialization::serialization::expr::NumericExpr;
+use scrapcore_serialization::serialization::DeserializeModel;
+use scrapcore_serialization::slabmap::SlabMap;
+use scrapcore_serialization::testing::{render_registry, snapshot_directory};
+use scrapcore_serialization::ItemId;
+use serde_json::json;
+use slabmap::entry::Entry;
+use slabmap::serialization::with_id_keys;
+use slabmap::{SlabMapId, SlabMapUntypedId};
 use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
 
 #[test]
 fn save_schema() {
-    let schema = schemars::schema_for!(CityItemSerialized);
+    let schema = PartialCityRegistry::default().items_schema();
     let data = serde_json::to_vec(&schema).unwrap();
     fs::write("./schema.json", data).unwrap()
 }
@@ -14,3 +52,1282 @@ fn load_test_db() {
         panic!("{}", err)
     }
 }
+
+#[test]
+fn expression_fields() {
+    let registry = match load_database("./test_db".as_ref()) {
+        Ok(registry) => registry,
+        Err(err) => panic!("{}", err),
+    };
+
+    let Some(Plot::Theater(theater)) = registry.plot.get_by_key("test:theater").map(|e| &e.data)
+    else {
+        panic!("Theater should be loaded")
+    };
+    assert_eq!(theater.seats, 90);
+}
+
+#[test]
+fn constant_fields() {
+    let registry = match load_database("./test_db".as_ref()) {
+        Ok(registry) => registry,
+        Err(err) => panic!("{}", err),
+    };
+
+    let Some(Plot::Theater(theater)) = registry.plot.get_by_key("test:theater").map(|e| &e.data)
+    else {
+        panic!("Theater should be loaded")
+    };
+    assert_eq!(theater.ticket_price, 12.5);
+}
+
+#[test]
+fn expression_missing_variable() {
+    let mut registry = PartialCityRegistry::default();
+    let theater: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Plot",
+        "id": "test:theater",
+        "Theater": {
+            "name": "Globe",
+            "seats": "base_seats * 2",
+            "ticketPrice": 10
+        }
+    }))
+    .unwrap();
+    registry.insert(Path::new("theater.json"), theater).unwrap();
+
+    let err = registry.into_registry().unwrap_err();
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::MissingVariable(name) if name == "base_seats"
+    ));
+    assert!(matches!(
+        err.stack.first(),
+        Some(DeserializationErrorStackItem::ExprVariable(name)) if name == "base_seats"
+    ));
+}
+
+#[test]
+fn expression_integer_overflow() {
+    let mut registry = PartialCityRegistry::default();
+    let eval = |registry: &mut PartialCityRegistry, expr: &str| {
+        NumericExpr::<i64>::Expr(expr.to_string()).deserialize(registry)
+    };
+    assert_eq!(eval(&mut registry, "-2^63").unwrap(), i64::MIN);
+    assert!(matches!(
+        eval(&mut registry, "2^63").unwrap_err().kind,
+        DeserializationErrorKind::BadExpression { .. }
+    ));
+    let max = NumericExpr::<u64>::Expr("2^64".to_string()).deserialize(&mut registry);
+    assert!(matches!(
+        max.unwrap_err().kind,
+        DeserializationErrorKind::BadExpression { .. }
+    ));
+    let max = NumericExpr::<u8>::Expr("2^8 - 1".to_string()).deserialize(&mut registry);
+    assert_eq!(max.unwrap(), u8::MAX);
+}
+
+#[test]
+fn constant_missing() {
+    let mut registry = PartialCityRegistry::default();
+    let theater: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Plot",
+        "id": "test:theater",
+        "Theater": {
+            "name": "Globe",
+            "seats": 100,
+            "ticketPrice": "$ticket_price"
+        }
+    }))
+    .unwrap();
+    registry.insert(Path::new("theater.json"), theater).unwrap();
+
+    let err = registry.into_registry().unwrap_err();
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::MissingVariable(name) if name == "ticket_price"
+    ));
+    assert!(matches!(
+        err.stack.first(),
+        Some(DeserializationErrorStackItem::ExprVariable(name)) if name == "ticket_price"
+    ));
+}
+
+#[test]
+fn constant_reference_syntax() {
+    let parse = |value| serde_json::from_value::<ConstOr<u32>>(value);
+    assert_eq!(parse(json!(12)).unwrap(), ConstOr::Value(12));
+    assert_eq!(
+        parse(json!("$seats")).unwrap(),
+        ConstOr::Const("seats".to_string())
+    );
+    assert!(parse(json!("12")).is_err());
+}
+
+#[test]
+fn constant_numeric_fields() {
+    // Numeric fields accept constants without any attributes
+    let mut registry = PartialCityRegistry::default();
+    let items = [
+        json!({ "type": "Person", "id": "test:alex", "name": "Alex" }),
+        json!({ "type": "Mayor", "person": "test:alex" }),
+        json!({ "type": "Variable", "id": "base_trees", "value": 7 }),
+        json!({ "type": "Variable", "id": "trees", "value": "$base_trees" }),
+        json!({ "type": "Plot", "id": "test:park", "Park": { "name": "Central", "trees": "$trees" } }),
+    ];
+    for (i, item) in items.into_iter().enumerate() {
+        let item: CityItemSerialized = serde_json::from_value(item).unwrap();
+        registry
+            .insert(Path::new(&format!("item_{i}.json")), item)
+            .unwrap();
+    }
+
+    let registry = registry.into_registry().unwrap();
+    let Plot::Park { trees, .. } = &registry.plot.get_by_key("test:park").unwrap().data else {
+        panic!("Park should be loaded")
+    };
+    assert_eq!(*trees, 7);
+}
+
+#[test]
+fn validator_non_empty() {
+    let mut registry = PartialCityRegistry::default();
+    let person: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Person",
+        "id": "test:nameless",
+        "name": ""
+    }))
+    .unwrap();
+    registry.insert(Path::new("person.json"), person).unwrap();
+
+    let err = registry.into_registry().unwrap_err();
+    assert!(matches!(&err.kind, DeserializationErrorKind::EmptyValue));
+    assert!(matches!(
+        err.stack.first(),
+        Some(DeserializationErrorStackItem::Field(name)) if *name == "name"
+    ));
+}
+
+#[test]
+fn validator_regex() {
+    let mut registry = PartialCityRegistry::default();
+    let theater: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Plot",
+        "id": "test:theater",
+        "Theater": {
+            "name": "globe",
+            "seats": 100,
+            "ticketPrice": 10
+        }
+    }))
+    .unwrap();
+    registry.insert(Path::new("theater.json"), theater).unwrap();
+
+    let err = registry.into_registry().unwrap_err();
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::RegexMismatch { got, .. } if got == "globe"
+    ));
+}
+
+/// Builds the registry from a single item, returning the error
+fn single_item_error(item: serde_json::Value) -> DeserializationError<PartialCityRegistry> {
+    let mut registry = PartialCityRegistry::default();
+    let item: CityItemSerialized = serde_json::from_value(item).unwrap();
+    registry.insert(Path::new("item.json"), item).unwrap();
+    registry.into_registry().unwrap_err()
+}
+
+#[test]
+fn validator_len_min() {
+    let err = single_item_error(json!({
+        "type": "Plot",
+        "id": "test:house",
+        "House": {
+            "residents": []
+        }
+    }));
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::LengthTooSmall { limit: 1, got: 0 }
+    ));
+    assert!(matches!(
+        err.stack.first(),
+        Some(DeserializationErrorStackItem::Field(name)) if *name == "residents"
+    ));
+}
+
+#[test]
+fn validator_len_max() {
+    let err = single_item_error(json!({
+        "type": "Person",
+        "id": "test:long",
+        "name": "a".repeat(65)
+    }));
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::LengthTooLarge { limit: 64, got: 65 }
+    ));
+}
+
+#[test]
+fn validator_one_of() {
+    let err = single_item_error(json!({
+        "type": "Plot",
+        "id": "test:theater",
+        "Theater": {
+            "name": "Globe",
+            "seats": 100,
+            "ticketPrice": 10,
+            "genre": "circus"
+        }
+    }));
+    let DeserializationErrorKind::NotOneOf { got, expected } = &err.kind else {
+        panic!("Unexpected error: {err}")
+    };
+    assert_eq!(got, "circus");
+    assert_eq!(expected, &["drama", "comedy", "opera"]);
+}
+
+#[test]
+fn validator_finite() {
+    // Too large for `f32`, so it's read as infinity
+    let err = single_item_error(json!({
+        "type": "Plot",
+        "id": "test:theater",
+        "Theater": {
+            "name": "Globe",
+            "seats": 100,
+            "ticketPrice": 1e300
+        }
+    }));
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::NonFiniteValue(value) if value.is_infinite()
+    ));
+}
+
+#[test]
+fn struct_validation() {
+    let mut registry = PartialCityRegistry::default();
+    let parent: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Person",
+        "id": "test:parent",
+        "name": "Parent"
+    }))
+    .unwrap();
+    let child: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Person",
+        "id": "test:child",
+        "name": "Child",
+        "mom": "test:parent",
+        "dad": "test:parent"
+    }))
+    .unwrap();
+    registry.insert(Path::new("parent.json"), parent).unwrap();
+    registry.insert(Path::new("child.json"), child).unwrap();
+
+    let err = registry.into_registry().unwrap_err();
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::ValidationFailed(_)
+    ));
+    assert!(matches!(
+        err.stack.first(),
+        Some(DeserializationErrorStackItem::ItemById(id, _)) if id == "test:child"
+    ));
+}
+
+#[test]
+fn struct_validation_own_id() {
+    let err = single_item_error(json!({
+        "type": "Person",
+        "id": "test:loop",
+        "name": "Loop",
+        "mom": "test:loop"
+    }));
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::ValidationFailed(reason) if reason.contains("own parent")
+    ));
+
+    // Inline residents get the context of the house, so a house sharing the
+    // key with the resident's mom is not mistaken for the resident
+    let mut registry = PartialCityRegistry::default();
+    let items = [
+        json!({
+            "type": "Plot",
+            "id": "test:alex",
+            "House": {
+                "residents": [{ "name": "Kim", "mom": "test:alex" }]
+            }
+        }),
+        json!({ "type": "Person", "id": "test:alex", "name": "Alex" }),
+        json!({ "type": "Mayor", "version": "1", "person": "test:alex" }),
+    ];
+    for (i, item) in items.into_iter().enumerate() {
+        let item: CityItemSerialized = serde_json::from_value(item).unwrap();
+        let path = format!("item_{i}.json");
+        registry.insert(Path::new(&path), item).unwrap();
+    }
+    registry.into_registry().unwrap();
+}
+
+#[test]
+fn default_fields() {
+    let registry = match load_database("./test_db".as_ref()) {
+        Ok(registry) => registry,
+        Err(err) => panic!("{}", err),
+    };
+
+    let alex = registry.person.get_by_key("test:alex").unwrap();
+    assert_eq!(registry.mayor.deputy, alex.id);
+    assert_eq!(registry.mayor.term, 0);
+}
+
+#[test]
+fn default_fields_null() {
+    let keeper = |park| match serde_json::from_value(json!({ "Park": park })).unwrap() {
+        PlotSerialized::Park { keeper, .. } => keeper,
+        _ => panic!("Park should be parsed"),
+    };
+    // Missing fields get the default, while `null` is kept as the value
+    assert!(keeper(json!({ "name": "Central" })).is_none());
+    assert!(matches!(
+        keeper(json!({ "name": "Central", "keeper": null })),
+        Some(None)
+    ));
+
+    let err = serde_json::from_value::<PlotSerialized>(json!({
+        "Park": { "name": "Central", "trees": null }
+    }));
+    assert!(err.is_err());
+}
+
+#[test]
+fn default_fields_schema() {
+    let schema = serde_json::to_value(schemars::schema_for!(CityItemSerialized)).unwrap();
+    let mayor = &schema["definitions"]["MayorSerialized"];
+    assert_eq!(mayor["required"], json!(["person"]));
+    assert_eq!(mayor["properties"]["term"]["default"], json!(0));
+    assert_eq!(mayor["properties"]["deputy"]["default"], json!("test:alex"));
+}
+
+#[test]
+fn custom_serde_naming() {
+    let weather: WeatherSerialized = serde_json::from_value(json!({
+        "kind": "rain",
+        "drops_per_hour": 5
+    }))
+    .unwrap();
+
+    let mut registry = PartialCityRegistry::default();
+    let Weather::Rain(rain) = weather.deserialize(&mut registry).unwrap() else {
+        panic!("Should be deserialized as rain")
+    };
+    assert_eq!(rain.drops_per_hour, 5);
+
+    let sunny: WeatherSerialized = serde_json::from_value(json!({ "kind": "sunny" })).unwrap();
+    assert!(matches!(sunny, WeatherSerialized::Sunny));
+}
+
+#[test]
+fn tuple_and_unit_structs() {
+    let mut registry = PartialCityRegistry::default();
+
+    let health: HealthSerialized = serde_json::from_value(json!(12.5)).unwrap();
+    let Health(health) = health.deserialize(&mut registry).unwrap();
+    assert_eq!(health, 12.5);
+
+    let _: LandmarkSerialized = serde_json::from_value(json!(null)).unwrap();
+
+    let coords: CoordinatesSerialized = serde_json::from_value(json!([-3, 4])).unwrap();
+    let Coordinates(x, y) = coords.deserialize(&mut registry).unwrap();
+    assert_eq!((x, y), (-3, 4));
+
+    let coords: CoordinatesSerialized = serde_json::from_value(json!([3, -4])).unwrap();
+    let err = DeserializeModel::<Coordinates, _>::deserialize(coords, &mut registry).unwrap_err();
+    assert!(matches!(
+        &err.kind,
+        DeserializationErrorKind::ValueTooSmall { .. }
+    ));
+    assert!(matches!(
+        err.stack.first(),
+        Some(DeserializationErrorStackItem::Index(1))
+    ));
+}
+
+#[test]
+fn transparent_struct() {
+    let mut registry = PartialCityRegistry::default();
+    let alex: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Person",
+        "id": "test:alex",
+        "name": "Alex"
+    }))
+    .unwrap();
+    let mayor: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Mayor",
+        "person": "test:alex"
+    }))
+    .unwrap();
+    registry.insert(Path::new("alex.json"), alex).unwrap();
+    registry.insert(Path::new("mayor.json"), mayor).unwrap();
+
+    let owner: OwnerSerialized = serde_json::from_value(json!("test:alex")).unwrap();
+    let owner: Owner = owner.deserialize(&mut registry).unwrap();
+    let registry = registry.into_registry().unwrap();
+    assert_eq!(registry.person.key_to_id("test:alex"), Some(owner.person));
+}
+
+#[test]
+fn struct_like_variants() {
+    let mut registry = PartialCityRegistry::default();
+    let park: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Plot",
+        "id": "test:park",
+        "Park": {
+            "name": ""
+        }
+    }))
+    .unwrap();
+    registry.insert(Path::new("park.json"), park).unwrap();
+
+    let err = registry.into_registry().unwrap_err();
+    assert!(matches!(&err.kind, DeserializationErrorKind::EmptyValue));
+    assert!(matches!(
+        err.stack.as_slice(),
+        [
+            DeserializationErrorStackItem::Field("name"),
+            DeserializationErrorStackItem::Variant("Park"),
+            ..
+        ]
+    ));
+}
+
+#[test]
+fn versioned_models() {
+    let registry = match load_database("./test_db".as_ref()) {
+        Ok(registry) => registry,
+        Err(err) => panic!("{}", err),
+    };
+    let alex = registry.person.get_by_key("test:alex").unwrap();
+    assert_eq!(registry.mayor.person, alex.id);
+
+    for mayor in [
+        json!({ "type": "Mayor", "version": "1", "person": "test:alex" }),
+        json!({ "type": "Mayor", "version": 1, "person": "test:alex" }),
+        json!({ "type": "Mayor", "version": "2", "person": "test:alex", "term": 2 }),
+        json!({ "type": "Mayor", "version": 2, "person": "test:alex", "term": 2 }),
+        json!({ "type": "Mayor", "person": "test:alex", "term": 2 }),
+    ] {
+        let mayor: CityItemSerialized = serde_json::from_value(mayor).unwrap();
+        assert!(matches!(mayor, CityItemSerialized::Mayor(_)));
+    }
+
+    // Unknown versions and data not matching the shape of its version are
+    // rejected instead of being read as the latest version
+    for mayor in [
+        json!({ "type": "Mayor", "version": "7", "person": "test:alex" }),
+        json!({ "type": "Mayor", "version": 0, "person": "test:alex" }),
+        json!({ "type": "Mayor", "version": "1", "deputy": "test:alex" }),
+        json!({ "type": "Mayor", "version": 1.5, "person": "test:alex" }),
+    ] {
+        let err = serde_json::from_value::<CityItemSerialized>(mayor.clone());
+        assert!(err.is_err(), "{mayor} should be rejected");
+    }
+    let err = serde_json::from_value::<CityItemSerialized>(
+        json!({ "type": "Mayor", "version": "7", "person": "test:alex" }),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("unknown model version `7`"));
+}
+
+#[test]
+fn registry_schema() {
+    let registry = match load_database("./test_db".as_ref()) {
+        Ok(registry) => registry,
+        Err(err) => panic!("{}", err),
+    };
+    let schema = serde_json::to_value(registry.items_schema()).unwrap();
+    let person_id = &schema["definitions"]["PersonId"];
+    assert_eq!(person_id["x-scrapcore-ref"], "Person");
+    assert_eq!(person_id["enum"], json!(["test:alex"]));
+
+    // No known keys leave the reference unrestricted
+    let registry = PartialCityRegistry::default();
+    let schema = serde_json::to_value(registry.items_schema()).unwrap();
+    assert_eq!(schema["definitions"]["PersonId"].get("enum"), None);
+}
+
+#[test]
+fn registry_schema_same_named_kinds() {
+    // Gallery registers its own `Person` type under the `Visitor` kind
+    let mut gallery = PartialGalleryRegistry::default();
+    let visitor: GalleryItemSerialized = serde_json::from_value(json!({
+        "type": "Visitor",
+        "id": "test:mona",
+        "name": "Mona"
+    }))
+    .unwrap();
+    gallery.insert(Path::new("mona.json"), visitor).unwrap();
+
+    let schema = serde_json::to_value(gallery.items_schema()).unwrap();
+    let visitor_id = &schema["definitions"]["PersonId"];
+    assert_eq!(visitor_id["x-scrapcore-ref"], "Visitor");
+    assert_eq!(visitor_id["enum"], json!(["test:mona"]));
+
+    // City persons share the type name, but are a different kind, so they
+    // don't restrict the gallery references
+    let mut city = PartialCityRegistry::default();
+    let alex: CityItemSerialized = serde_json::from_value(json!({
+        "type": "Person",
+        "id": "test:alex",
+        "name": "Alex"
+    }))
+    .unwrap();
+    city.insert(Path::new("alex.json"), alex).unwrap();
+
+    let schema = city
+        .schema_references()
+        .schema_for::<GalleryItemSerialized>();
+    let schema = serde_json::to_value(schema).unwrap();
+    let visitor_id = &schema["definitions"]["PersonId"];
+    assert_eq!(visitor_id.get("x-scrapcore-ref"), None);
+    assert_eq!(visitor_id.get("enum"), None);
+}
+
+#[test]
+fn schema_reference_annotations() {
+    let schema = serde_json::to_value(PartialCityRegistry::default().items_schema()).unwrap();
+    let definitions = &schema["definitions"];
+
+    let person = &definitions["PersonSerialized"];
+    assert_eq!(
+        person["properties"]["mom"]["anyOf"][0]["x-scrapcore-ref"],
+        "Person"
+    );
+    assert!(!person["required"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("mom")));
+    assert_eq!(
+        definitions["MayorSerialized"]["properties"]["deputy"]["anyOf"][0]["x-scrapcore-ref"],
+        "Person"
+    );
+
+    let keeper = &definitions["InlineOrId_for_PersonSerialized"]["anyOf"][0]["$ref"];
+    assert_eq!(keeper, "#/definitions/PersonId");
+    assert_eq!(definitions["PersonId"]["x-scrapcore-ref"], "Person");
+}
+
+#[test]
+fn validator_cli() {
+    let run_cli = |args: &[&str]| {
+        let mut out = vec![];
+        let mut err = vec![];
+        let result = run::<PartialCityRegistry>(args.iter().map(Into::into), &mut out, &mut err);
+        (
+            result,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    };
+
+    let (result, out, err) = run_cli(&["validate", "./test_db"]);
+    assert!(result.is_ok());
+    assert_eq!(out, "Content is valid\n");
+    assert_eq!(err, "");
+
+    let (result, out, _) = run_cli(&["stats", "./test_db"]);
+    assert!(result.is_ok());
+    assert!(out.contains("Person: 1\n"));
+    assert!(out.contains("Mayor: 1\n"));
+
+    let (result, out, _) = run_cli(&["schema", "./test_db"]);
+    assert!(result.is_ok());
+    let schema: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(
+        schema["definitions"]["PersonId"]["enum"],
+        json!(["test:alex"])
+    );
+
+    let (result, out, err) = run_cli(&["validate", "./missing_db"]);
+    assert!(matches!(result, Err(CliFailure::Invalid(1))));
+    assert_eq!(out, "");
+    assert!(err.contains("missing_db"), "{err}");
+
+    let (result, out, err) = run_cli(&["validate", "./test_db_invalid"]);
+    let Err(CliFailure::Invalid(count)) = result else {
+        panic!("Invalid content should fail validation");
+    };
+    assert_eq!(out, "");
+    assert!(count > 0);
+    assert!(!err.is_empty());
+
+    let (result, _, _) = run_cli(&["frobnicate", "./test_db"]);
+    assert!(matches!(result, Err(CliFailure::Usage(_))));
+}
+
+#[test]
+fn registry_snapshot() {
+    insta::assert_snapshot!(snapshot_directory::<PartialCityRegistry>(
+        "./test_db".as_ref()
+    ));
+}
+
+#[test]
+fn registry_snapshot_stale_ids() {
+    let mut registry = load_database("./test_db".as_ref()).unwrap();
+    let alex = registry.person.key_to_id("test:alex").unwrap();
+    registry.mayor.deputy = SlabMapUntypedId::from_parts(alex.raw(), alex.generation() + 1)
+        .unwrap()
+        .as_typed_unchecked();
+
+    // Stale ids are printed as they are, rather than as keys of the items
+    // occupying their slots
+    let snapshot = render_registry(&registry);
+    assert!(snapshot.contains("person: \"test:alex\","));
+    assert!(snapshot.contains("deputy: SlabMapId<"));
+}
+
+#[test]
+fn registry_errors_snapshot() {
+    insta::assert_snapshot!(snapshot_directory::<PartialCityRegistry>(
+        "./test_db_invalid".as_ref()
+    ));
+}
+
+#[test]
+fn arbitrary_registries() {
+    let mut pool = KeyPool::default();
+    pool.add_items::<Person>(["test:alex", "test:sam"]);
+    pool.add_items::<Plot>(["test:theater", "test:park"]);
+    pool.add_items::<Variable>(["base_seats", "ticket_price"]);
+    pool.add_variables(["base_seats", "ticket_price"]);
+
+    check_registry::<PartialCityRegistry>(&pool, 256);
+}
+
+#[test]
+fn slabmap_generational_ids() {
+    let mut map = SlabMap::<String, u32>::default();
+    let a = map.insert_new("a".to_string(), 1).unwrap();
+    let b = map.insert_new("b".to_string(), 2).unwrap();
+    assert_eq!((a.raw(), b.raw()), (0, 1));
+
+    assert_eq!(map.remove("a"), Some((a, 1)));
+    assert_eq!(map.get_by_id(a), None);
+    assert_eq!(map.remove_by_id(a), None);
+    assert_eq!(map.len(), 1);
+
+    // Slot of the removed value is reused, but the old id stays stale
+    let c = map.insert_new("c".to_string(), 3).unwrap();
+    assert_eq!(c.raw(), a.raw());
+    assert_ne!(c, a);
+    assert_eq!(map.get_by_id(a), None);
+    assert_eq!(map.id_to_key(a), None);
+    assert_eq!(map[c], 3);
+    assert_eq!(map.key_to_id("c"), Some(c));
+    assert_eq!(map.ids().collect::<Vec<_>>(), vec![c, b]);
+    assert_eq!(format!("{c:?}"), "SlabMapId<u32>(0, 1)");
+
+    assert_eq!(map.remove_by_id(b), Some(("b".to_string(), 2)));
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec!["c"]);
+}
+
+#[test]
+#[cfg(feature = "compact_ids")]
+fn slabmap_compact_ids() {
+    assert_eq!(size_of::<Option<SlabMapId<u32>>>(), 4);
+
+    let mut map = SlabMap::<String, u32>::default();
+    let first = map.insert_new("a".to_string(), 0).unwrap();
+    for i in 1..=slabmap::MAX_GENERATION {
+        map.remove("a").unwrap();
+        let id = map.insert_new("a".to_string(), i).unwrap();
+        assert_eq!((id.raw(), id.generation()), (0, i));
+    }
+    // Slot ran out of generations, and is retired instead of wrapping around
+    map.remove("a").unwrap();
+    let id = map.insert_new("a".to_string(), 0).unwrap();
+    assert_eq!(id.raw(), 1);
+    assert!(!map.contains_id(first));
+
+    let out_of_range = json!([slabmap::MAX_INDEX + 1, 0]);
+    assert!(serde_json::from_value::<SlabMapId<u32>>(out_of_range).is_err());
+}
+
+#[test]
+fn slabmap_entries() {
+    let mut map = SlabMap::<String, (usize, u32)>::default();
+    let value = map
+        .entry("a".to_string())
+        .or_insert_with_id(|id| (id.raw(), 1));
+    assert_eq!(*value, (0, 1));
+
+    let entry = map.entry("a".to_string()).and_modify(|value| value.1 += 1);
+    assert!(matches!(entry, Entry::Occupied(_)));
+    assert_eq!(*entry.or_insert((9, 9)), (0, 2));
+
+    let Entry::Vacant(entry) = map.entry("b".to_string()) else {
+        panic!("Entry should be vacant")
+    };
+    let id = entry.id();
+    entry.insert((id.raw(), 0));
+    assert_eq!(map[id], (1, 0));
+
+    let Entry::Occupied(entry) = map.entry("a".to_string()) else {
+        panic!("Entry should be occupied")
+    };
+    assert_eq!(entry.remove(), ("a".to_string(), (0, 2)));
+    // Vacant entries report the reused slot with the bumped generation
+    let entry = map.entry("c".to_string());
+    assert_eq!((entry.id().raw(), entry.id().generation()), (0, 1));
+    assert!(map.insert_new("b".to_string(), (0, 0)).is_err());
+}
+
+#[test]
+fn interned_item_keys() {
+    let a = ItemId::new("test:interned");
+    let b = ItemId::from("test:interned".to_string());
+    assert_eq!(a, b);
+    assert!(std::ptr::eq(a.as_str(), b.as_str()));
+    assert_ne!(a, ItemId::new("test:other"));
+
+    let deserialized: Vec<ItemId> =
+        serde_json::from_value(json!(["test:interned", "test:interned"])).unwrap();
+    assert!(deserialized
+        .iter()
+        .all(|key| std::ptr::eq(key.as_str(), a.as_str())));
+
+    let mut map = SlabMap::<ItemId, u32>::default();
+    map.insert_new(a.clone(), 1).unwrap();
+    assert_eq!(map.get_by_key("test:interned"), Some(&1));
+    assert_eq!(serde_json::to_value(a).unwrap(), json!("test:interned"));
+
+    // Strings are released with their last copy
+    let dropped = ItemId::new("test:interned_dropped");
+    let copy = dropped.clone();
+    drop(dropped);
+    assert!(ItemId::is_interned("test:interned_dropped"));
+    drop(copy);
+    assert!(!ItemId::is_interned("test:interned_dropped"));
+}
+
+#[test]
+fn parallel_iteration() {
+    let mut map = SlabMap::<String, u32>::default();
+    for i in 0..100 {
+        map.insert_new(format!("key_{i}"), i).unwrap();
+    }
+    map.remove("key_10");
+    let mut expected: Vec<_> = map.iter().map(|(id, value)| (id, *value)).collect();
+    let mut actual: Vec<_> = map.par_iter().map(|(id, value)| (id, *value)).collect();
+    actual.sort_by_key(|(id, _)| id.raw());
+    assert_eq!(actual, expected);
+    assert_eq!(map.par_values().sum::<u32>(), 4940);
+
+    map.par_iter_mut().for_each(|(_, value)| *value *= 2);
+    expected.iter_mut().for_each(|(_, value)| *value *= 2);
+    let actual: Vec<_> = map.iter().map(|(id, value)| (id, *value)).collect();
+    assert_eq!(actual, expected);
+
+    let mut registry = load_database("./test_db".as_ref()).unwrap();
+    let names: usize = CollectionHolder::<Person>::par_items(&registry)
+        .map(|person| person.name.len())
+        .sum();
+    assert_eq!(
+        names,
+        registry
+            .person
+            .values()
+            .map(|e| e.data.name.len())
+            .sum::<usize>()
+    );
+    CollectionHolder::<Person>::par_iter_items_mut(&mut registry)
+        .for_each(|(_, person)| person.name.make_ascii_uppercase());
+    let ids: Vec<_> = CollectionHolder::<Person>::par_iter_items(&registry)
+        .map(|(id, person)| {
+            assert_eq!(person.name, person.name.to_ascii_uppercase());
+            id
+        })
+        .collect();
+    assert_eq!(ids, registry.person.ids().collect::<Vec<_>>());
+}
+
+#[test]
+fn slabmap_shared_between_threads() {
+    let mut map = SlabMap::<String, u32>::default();
+    for i in 0..64 {
+        map.insert_new(format!("key_{i}"), i).unwrap();
+    }
+    map.remove("key_7");
+
+    let map = &map;
+    let found = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..4)
+            .map(|worker| {
+                scope.spawn(move || {
+                    (worker..64)
+                        .step_by(4)
+                        .filter(|i| map.get_by_key(format!("key_{i}").as_str()) == Some(i))
+                        .count()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .sum::<usize>()
+    });
+    assert_eq!(found, 63);
+
+    let moved = std::thread::spawn({
+        let map = map.clone();
+        move || map.into_iter().map(|(_, _, value)| value).sum::<u32>()
+    });
+    assert_eq!(moved.join().unwrap(), (0..64).sum::<u32>() - 7);
+}
+
+#[test]
+fn slabmap_serde_roundtrip() {
+    let mut map = SlabMap::<String, u32>::default();
+    let a = map.insert_new("a".to_string(), 1).unwrap();
+    let b = map.insert_new("b".to_string(), 2).unwrap();
+    map.remove_by_id(a);
+
+    let data = serde_json::to_value(&map).unwrap();
+    assert_eq!(
+        data,
+        json!([{"generation": 1, "free": 0}, {"key": "b", "value": 2}])
+    );
+
+    let mut restored: SlabMap<String, u32> = serde_json::from_value(data).unwrap();
+    assert_eq!(restored.get_by_id(a), None);
+    assert_eq!(restored[b], 2);
+    assert_eq!(restored.key_to_id("b"), Some(b));
+    // Vacant slot is reused after loading, with its generation preserved
+    let c = restored.insert_new("c".to_string(), 3).unwrap();
+    assert_eq!((c.raw(), c.generation()), (0, 1));
+
+    // Slots are reused in the same order as without the round trip
+    let mut original = SlabMap::<String, u32>::default();
+    let ids: Vec<_> = (0..5)
+        .map(|i| original.insert_new(i.to_string(), i).unwrap())
+        .collect();
+    for i in [3, 0, 4, 1] {
+        original.remove_by_id(ids[i]);
+    }
+    let mut reloaded: SlabMap<String, u32> =
+        serde_json::from_value(serde_json::to_value(&original).unwrap()).unwrap();
+    for i in 5..10 {
+        let expected = original.insert_new(i.to_string(), i).unwrap();
+        assert_eq!(reloaded.insert_new(i.to_string(), i).unwrap(), expected);
+    }
+
+    // Ids are serialized as raw pairs by default, and as keys inside `with_id_keys`
+    assert_eq!(serde_json::to_value(b).unwrap(), json!([1, 0]));
+    with_id_keys(&restored, || {
+        assert_eq!(serde_json::to_value(c).unwrap(), json!("c"));
+        assert!(serde_json::to_value(a).is_err());
+        let id: SlabMapId<u32> = serde_json::from_value(json!("b")).unwrap();
+        assert_eq!(id, b);
+        assert!(serde_json::from_value::<SlabMapId<u32>>(json!("d")).is_err());
+    });
+    let id: SlabMapId<u32> = serde_json::from_value(json!([1, 0])).unwrap();
+    assert_eq!(id, b);
+
+    let invalid = json!([{"key": "a"}]);
+    assert!(serde_json::from_value::<SlabMap<String, u32>>(invalid).is_err());
+    let duplicate = json!([{"key": "a", "value": 1}, {"key": "a", "value": 2}]);
+    assert!(serde_json::from_value::<SlabMap<String, u32>>(duplicate).is_err());
+}
+
+fn remapping_registry() -> CityRegistry {
+    let mut registry = PartialCityRegistry::default();
+    let items = [
+        json!({ "type": "Person", "id": "test:zoe", "name": "Zoe" }),
+        json!({ "type": "Person", "id": "test:kim", "name": "Kim" }),
+        json!({ "type": "Person", "id": "test:alex", "name": "Alex", "mom": "test:zoe" }),
+        json!({ "type": "Person", "id": "test:sam", "name": "Sam", "mom": "test:alex", "dad": "test:zoe" }),
+        json!({ "type": "Plot", "id": "test:park", "Park": { "name": "Park", "keeper": "test:sam" } }),
+        json!({ "type": "Mayor", "person": "test:zoe", "deputy": "test:sam" }),
+    ];
+    for (i, item) in items.into_iter().enumerate() {
+        let item: CityItemSerialized = serde_json::from_value(item).unwrap();
+        registry
+            .insert(Path::new(&format!("item_{i}.json")), item)
+            .unwrap();
+    }
+    registry.into_registry().unwrap()
+}
+
+#[test]
+fn registry_collection_remapping() {
+    let mut registry = remapping_registry();
+    let old_sam = registry.person.key_to_id("test:sam").unwrap();
+
+    let remap = sort_collection::<Person, _, _>(&mut registry, |key, _| key.clone()).unwrap();
+    let keys: Vec<_> = registry.person.keys().map(|key| key.as_str()).collect();
+    assert_eq!(keys, ["test:alex", "test:kim", "test:sam", "test:zoe"]);
+    assert_eq!(
+        remap.remap_id(old_sam),
+        registry.person.key_to_id("test:sam")
+    );
+
+    let key_of = |registry: &CityRegistry, id| registry.person.id_to_key(id).unwrap().clone();
+    for (id, entry) in registry.person.iter() {
+        assert_eq!(entry.id, id);
+    }
+    let sam = &registry.person.get_by_key("test:sam").unwrap().data;
+    assert_eq!(key_of(&registry, sam.mom.unwrap()), "test:alex");
+    assert_eq!(key_of(&registry, sam.dad.unwrap()), "test:zoe");
+    assert_eq!(key_of(&registry, registry.mayor.person), "test:zoe");
+    assert_eq!(key_of(&registry, registry.mayor.deputy), "test:sam");
+    let Plot::Park {
+        keeper: Some(InlineOrId::Id(keeper)),
+        ..
+    } = &registry.plot.get_by_key("test:park").unwrap().data
+    else {
+        panic!("Park keeper should be an id")
+    };
+    assert_eq!(key_of(&registry, *keeper), "test:sam");
+
+    // Compaction after removal shifts the following items
+    registry.person.remove("test:kim");
+    compact_collection::<Person, _>(&mut registry).unwrap();
+    let ids: Vec<_> = registry
+        .person
+        .ids()
+        .map(|id| (id.raw(), id.generation()))
+        .collect();
+    assert_eq!(ids, [(0, 0), (1, 0), (2, 0)]);
+    assert_eq!(key_of(&registry, registry.mayor.deputy), "test:sam");
+    assert!(compact_collection::<Person, _>(&mut registry)
+        .unwrap()
+        .get::<Person>()
+        .unwrap()
+        .is_identity());
+}
+
+#[test]
+fn registry_remapping_dangling_ids() {
+    let mut registry = remapping_registry();
+    let key_of = |registry: &CityRegistry, id| registry.person.id_to_key(id).unwrap().clone();
+
+    // Removal leaves Sam's mom dangling, and compaction moves Sam into the
+    // freed slot, so the dangling id would otherwise point to Sam
+    let (alex, _) = registry.person.remove("test:alex").unwrap();
+    let remap = compact_collection::<Person, _>(&mut registry).unwrap();
+    assert_eq!(remap.remap_id(alex), None);
+    assert_eq!(
+        registry.person.key_to_id("test:sam").unwrap().raw(),
+        alex.raw()
+    );
+    let sam = &registry.person.get_by_key("test:sam").unwrap().data;
+    assert_eq!(sam.mom, None);
+    assert_eq!(key_of(&registry, sam.dad.unwrap()), "test:zoe");
+
+    // Mayor can't be cleared
+    registry.person.remove("test:zoe").unwrap();
+    let err = compact_collection::<Person, _>(&mut registry).unwrap_err();
+    assert_eq!(err.kind, std::any::type_name::<Person>());
+}
+
+#[test]
+fn bevy_strong_handles() {
+    let mut folders = Assets::<LoadedFolder>::default();
+    let mut registry = PartialGalleryRegistry::default();
+    let handle = folders.add(LoadedFolder { handles: vec![] });
+    asset_insert(&mut registry, Path::new("oak.png").into(), handle).unwrap();
+
+    let item: GalleryItemSerialized = serde_json::from_value(json!({
+        "type": "Painting",
+        "id": "test:mona",
+        "frame": "oak.png",
+        "strongFrame": "oak.png"
+    }))
+    .unwrap();
+    registry.insert(Path::new("item.json"), item).unwrap();
+    let registry = registry.into_registry().unwrap();
+
+    let painting = &registry.painting.get_by_key("test:mona").unwrap().data;
+    assert!(painting.frame.is_weak());
+    assert!(painting.strong_frame.is_strong());
+}
+
+/// Starts an app loading the city registry with the plugin, and waits for
+/// the first build
+fn registry_app(plugin: RegistryPlugin<PartialCityRegistry>) -> App {
+    AsyncComputeTaskPool::get_or_init(TaskPool::new);
+    ComputeTaskPool::get_or_init(TaskPool::new);
+    IoTaskPool::get_or_init(TaskPool::new);
+
+    let mut app = App::new();
+    app.add_plugins((
+        AssetPlugin {
+            file_path: ".".to_string(),
+            ..Default::default()
+        },
+        plugin,
+    ));
+    update_until(&mut app, |app| {
+        app.world
+            .contains_resource::<LoadedRegistry<CityRegistry>>()
+    });
+    app
+}
+
+/// Updates the app until the condition holds
+fn update_until(app: &mut App, mut condition: impl FnMut(&mut App) -> bool) {
+    for _ in 0..500 {
+        app.update();
+        if condition(app) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Condition was not met in time");
+}
+
+/// Replaces items of the source loaded from `path`, as if the file was
+/// modified
+fn modify_source(app: &mut App, path: &str, items: impl IntoIterator<Item = serde_json::Value>) {
+    let mut sources = app
+        .world
+        .resource_mut::<Assets<RegistrySource<PartialCityRegistry>>>();
+    let id = sources
+        .iter()
+        .find(|(_, source)| {
+            source
+                .items
+                .iter()
+                .any(|(p, _)| p.to_string().starts_with(path))
+        })
+        .map(|(id, _)| id)
+        .expect("Source should be loaded");
+    let source = sources.get_mut(id).unwrap();
+    source.items = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let path = PathIdentifier::from_components([path, &i.to_string()]);
+            (path, serde_json::from_value(item).unwrap())
+        })
+        .collect();
+}
+
+/// Removes the source loaded from `path`, as if the file was deleted
+fn remove_source(app: &mut App, path: &str) {
+    let mut sources = app
+        .world
+        .resource_mut::<Assets<RegistrySource<PartialCityRegistry>>>();
+    let id = sources
+        .iter()
+        .find(|(_, source)| {
+            source
+                .items
+                .iter()
+                .any(|(p, _)| p.to_string().starts_with(path))
+        })
+        .map(|(id, _)| id)
+        .expect("Source should be loaded");
+    sources.remove(id);
+}
+
+/// Updates the app until the registry is reloaded
+fn next_reload(app: &mut App) -> RegistryReloaded<PartialCityRegistry> {
+    let mut reader = app
+        .world
+        .resource::<Events<RegistryReloaded<PartialCityRegistry>>>()
+        .get_reader_current();
+    let mut reloaded = None;
+    update_until(app, |app| {
+        let events = app
+            .world
+            .resource::<Events<RegistryReloaded<PartialCityRegistry>>>();
+        reloaded = reader.read(events).last().map(|event| RegistryReloaded {
+            changed: event.changed.clone(),
+            ids_changed: event.ids_changed,
+        });
+        reloaded.is_some()
+    });
+    reloaded.unwrap()
+}
+
+#[test]
+fn bevy_plugin() {
+    let app = registry_app(RegistryPlugin::folder("test_db").with_extensions(["json"]));
+
+    let registry = app.world.resource::<LoadedRegistry<CityRegistry>>();
+    assert!(registry.person.get_by_key("test:alex").is_some());
+    assert_eq!(
+        *registry.person.id_to_key(registry.mayor.person).unwrap(),
+        "test:alex"
+    );
+}
+
+#[test]
+fn bevy_plugin_bundle() {
+    let app = registry_app(RegistryPlugin::bundle("test_bundle.json"));
+
+    let registry = app.world.resource::<LoadedRegistry<CityRegistry>>();
+    assert_eq!(
+        registry.person.get_by_key("test:alex").unwrap().data.name,
+        "Alex"
+    );
+    assert_eq!(
+        *registry.person.id_to_key(registry.mayor.person).unwrap(),
+        "test:alex"
+    );
+    // The bundle holds the mayor in the latest shape, with an integer tag
+    assert_eq!(registry.mayor.term, 2);
+}
+
+#[test]
+fn bevy_plugin_hot_reload() {
+    let mut app = registry_app(RegistryPlugin::folder("test_db").with_extensions(["json"]));
+    let alex = app
+        .world
+        .resource::<LoadedRegistry<CityRegistry>>()
+        .person
+        .key_to_id("test:alex");
+
+    // Adding items keeps ids of the existing ones
+    modify_source(
+        &mut app,
+        "test_db/person.json",
+        [
+            json!({ "type": "Person", "id": "test:alex", "name": "Alexander" }),
+            json!({ "type": "Person", "id": "test:kim", "name": "Kim" }),
+        ],
+    );
+    let reloaded = next_reload(&mut app);
+    assert!(!reloaded.ids_changed);
+    assert_eq!(
+        reloaded.changed,
+        vec![
+            (CityItemKind::Person, Some(ItemId::from("test:alex"))),
+            (CityItemKind::Person, Some(ItemId::from("test:kim"))),
+        ]
+    );
+    let registry = app.world.resource::<LoadedRegistry<CityRegistry>>();
+    assert_eq!(registry.person.key_to_id("test:alex"), alex);
+    assert_eq!(
+        registry.person.get_by_key("test:alex").unwrap().data.name,
+        "Alexander"
+    );
+
+    // Removing items rebuilds the registry from scratch
+    modify_source(
+        &mut app,
+        "test_db/person.json",
+        [json!({ "type": "Person", "id": "test:alex", "name": "Alexander" })],
+    );
+    let reloaded = next_reload(&mut app);
+    assert!(reloaded.ids_changed);
+    assert_eq!(
+        reloaded.changed,
+        vec![(CityItemKind::Person, Some(ItemId::from("test:kim")))]
+    );
+    let registry = app.world.resource::<LoadedRegistry<CityRegistry>>();
+    assert!(registry.person.get_by_key("test:kim").is_none());
+}
+
+#[test]
+fn bevy_plugin_removed_source() {
+    let mut app = registry_app(RegistryPlugin::folder("test_db").with_extensions(["json"]));
+
+    remove_source(&mut app, "test_db/theater.json");
+    let reloaded = next_reload(&mut app);
+    assert!(reloaded.ids_changed);
+    assert_eq!(
+        reloaded.changed,
+        vec![(CityItemKind::Plot, Some(ItemId::from("test:theater")))]
+    );
+    let registry = app.world.resource::<LoadedRegistry<CityRegistry>>();
+    assert!(registry.plot.get_by_key("test:theater").is_none());
+    assert!(registry.person.get_by_key("test:alex").is_some());
+}
+
+#[test]
+fn bevy_plugin_errors() {
+    let mut app = registry_app(RegistryPlugin::folder("test_db").with_extensions(["json"]));
+    // Systems run on the main thread, so the logs reach the local subscriber
+    app.edit_schedule(Update, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+    let logs = LogCollector::default();
+    let _guard = tracing::subscriber::set_default(logs.clone());
+
+    modify_source(
+        &mut app,
+        "test_db/person.json",
+        [json!({ "type": "Person", "id": "test:alex", "name": "" })],
+    );
+    update_until(&mut app, |_| {
+        logs.messages()
+            .iter()
+            .any(|message| message.starts_with("ERROR Failed to load"))
+    });
+
+    // The previous registry is kept in place
+    let registry = app.world.resource::<LoadedRegistry<CityRegistry>>();
+    assert_eq!(
+        registry.person.get_by_key("test:alex").unwrap().data.name,
+        "Alex"
+    );
+    let events = app
+        .world
+        .resource::<Events<RegistryReloaded<PartialCityRegistry>>>();
+    assert!(events.is_empty());
+}
+
+/// Subscriber collecting levels and messages of the logged events
+#[derive(Debug, Clone, Default)]
+struct LogCollector(Arc<Mutex<Vec<String>>>);
+
+impl LogCollector {
+    fn messages(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl tracing::Subscriber for LogCollector {
+    fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        struct Message(String);
+
+        impl Visit for Message {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    self.0 = format!("{value:?}");
+                }
+            }
+        }
+
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        let level = event.metadata().level();
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{level} {}", message.0));
+    }
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
+}
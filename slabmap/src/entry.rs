@@ -0,0 +1,163 @@
+//! Entry API of [SlabMap], see [SlabMap::entry]
+use std::hash::{BuildHasher, Hash};
+
+use crate::{SlabMap, SlabMapId};
+
+/// Entry of a single key in a [SlabMap], either holding a value or vacant
+pub enum Entry<'a, K: Eq + Hash, V, Hasher: BuildHasher> {
+    Occupied(OccupiedEntry<'a, K, V, Hasher>),
+    Vacant(VacantEntry<'a, K, V, Hasher>),
+}
+
+/// Entry of a key that is present in the map
+pub struct OccupiedEntry<'a, K: Eq + Hash, V, Hasher: BuildHasher> {
+    pub(crate) map: &'a mut SlabMap<K, V, Hasher>,
+    pub(crate) id: SlabMapId<V>,
+    /// Key the entry was requested with
+    pub(crate) key: K,
+}
+
+/// Entry of a key that is absent from the map
+pub struct VacantEntry<'a, K: Eq + Hash, V, Hasher: BuildHasher> {
+    pub(crate) map: &'a mut SlabMap<K, V, Hasher>,
+    pub(crate) hash: u64,
+    pub(crate) key: K,
+}
+
+impl<'a, K: Eq + Hash, V, Hasher: BuildHasher> Entry<'a, K, V, Hasher> {
+    /// Id of the value, or the id the value is going to get if the entry is
+    /// vacant
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX](crate::MAX_INDEX) + 1 slots
+    pub fn id(&self) -> SlabMapId<V> {
+        match self {
+            Entry::Occupied(entry) => entry.id(),
+            Entry::Vacant(entry) => entry.id(),
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with_id(|_| value)
+    }
+
+    pub fn or_insert_with(self, value: impl FnOnce() -> V) -> &'a mut V {
+        self.or_insert_with_id(|_| value())
+    }
+
+    /// Inserts the value built from its future id if the entry is vacant,
+    /// returning the reference to the value in the entry
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX](crate::MAX_INDEX) + 1 slots
+    pub fn or_insert_with_id(self, value: impl FnOnce(SlabMapId<V>) -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert_with_id(value),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Modifies the value of the occupied entry
+    pub fn and_modify(mut self, func: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            func(entry.get_mut())
+        }
+        self
+    }
+}
+
+impl<'a, K: Eq + Hash, V, Hasher: BuildHasher> OccupiedEntry<'a, K, V, Hasher> {
+    pub fn id(&self) -> SlabMapId<V> {
+        self.id
+    }
+
+    /// Key stored in the map
+    pub fn key(&self) -> &K {
+        self.map.slots[self.id.raw()].key()
+    }
+
+    /// Takes back the key the entry was requested with
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map[self.id]
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map[self.id]
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map[self.id]
+    }
+
+    /// Replaces the value, returning the old one. The id of the value is
+    /// kept
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the value, see [SlabMap::remove_by_id]
+    pub fn remove(self) -> (K, V) {
+        self.map
+            .remove_by_id(self.id)
+            .expect("Occupied entry should point to an existing value")
+    }
+}
+
+impl<'a, K: Eq + Hash, V, Hasher: BuildHasher> VacantEntry<'a, K, V, Hasher> {
+    /// Id the value is going to get once inserted
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX](crate::MAX_INDEX) + 1 slots
+    pub fn id(&self) -> SlabMapId<V> {
+        self.map.next_id()
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.insert_with_id(|_| value)
+    }
+
+    /// Inserts the value built from its id, returning the reference to it
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX](crate::MAX_INDEX) + 1 slots
+    pub fn insert_with_id(self, value: impl FnOnce(SlabMapId<V>) -> V) -> &'a mut V {
+        let id = self.map.next_id();
+        let value = value(id);
+        self.map.fill_slot_hashed(id, self.hash, self.key, value);
+        &mut self.map[id]
+    }
+}
@@ -1,31 +1,68 @@
+use std::any::type_name;
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
 use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
 use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
-use std::rc::Rc;
 
-#[cfg(feature = "serde")]
-use serde::Deserializer;
-
-use mem::{Ref, Wrapper};
+use entry::{Entry, OccupiedEntry, VacantEntry};
+use hashbrown::HashTable;
+use raw_id::RawId;
+pub use raw_id::{MAX_GENERATION, MAX_INDEX};
 
-mod mem;
-
-#[derive(Debug)]
-pub struct SlabMapId<V>(usize, PhantomData<V>);
+pub mod entry;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+mod raw_id;
+#[cfg(feature = "serde")]
+pub mod serialization;
+
+/// Id of a value in a [SlabMap]
+///
+/// Ids consist of the slot index and the generation of the slot. Removing a
+/// value bumps the generation of its slot, so ids of removed values never
+/// resolve to the values inserted into the reused slot later
+///
+/// Slots are only reused after removals, so maps that never remove values
+/// hand out dense ids matching the insertion order, with all generations
+/// being zero
+///
+/// With the `compact_ids` feature ids are 4 bytes large, including `Option`s
+/// of ids, at the cost of limiting the number of slots, see [MAX_INDEX]
+// `fn() -> V` keeps ids `Send` and `Sync` regardless of the value type
+pub struct SlabMapId<V>(RawId, PhantomData<fn() -> V>);
 
 impl<V> SlabMapId<V> {
-    fn new(id: usize) -> Self {
-        Self(id, Default::default())
+    fn new(id: usize, generation: u32) -> Self {
+        Self(RawId::new(id, generation), Default::default())
     }
 
+    /// Slot index of the id
     pub fn raw(&self) -> usize {
-        self.0
+        self.0.index()
+    }
+
+    /// Generation of the slot this id was issued for
+    pub fn generation(&self) -> u32 {
+        self.0.generation()
     }
 
     pub fn as_untyped(&self) -> SlabMapUntypedId {
-        SlabMapUntypedId::new(self.0)
+        SlabMapUntypedId(self.0)
+    }
+}
+
+impl<V> Debug for SlabMapId<V> {
+    // Ids are printed on a single line even in the alternate mode, keeping
+    // them easy to find in the debug output of the structures holding them
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SlabMapId<{}>({}", type_name::<V>(), self.raw())?;
+        // Generations are only printed once slots get reused, keeping output
+        // of maps without removals short
+        if self.generation() != 0 {
+            write!(f, ", {}", self.generation())?;
+        }
+        f.write_str(")")
     }
 }
 
@@ -38,8 +75,9 @@ impl<V> PartialEq for SlabMapId<V> {
 impl<V> Eq for SlabMapId<V> {}
 
 impl<V> Hash for SlabMapId<V> {
+    // Only the index is hashed, keeping ids usable with `nohash_hasher`
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.hash(state)
+        self.raw().hash(state)
     }
 }
 
@@ -54,15 +92,25 @@ impl<T> Copy for SlabMapId<T> {}
 impl<V> nohash_hasher::IsEnabled for SlabMapId<V> {}
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct SlabMapUntypedId(usize);
+pub struct SlabMapUntypedId(RawId);
 
 impl SlabMapUntypedId {
-    fn new(id: usize) -> Self {
-        Self(id)
+    fn new(id: usize, generation: u32) -> Self {
+        Self(RawId::new(id, generation))
+    }
+
+    /// Id with the given slot index and generation, or `None` if either of
+    /// them is out of range
+    pub fn from_parts(index: usize, generation: u32) -> Option<SlabMapUntypedId> {
+        RawId::checked(index, generation).map(Self)
     }
 
     pub fn raw(&self) -> usize {
-        self.0
+        self.0.index()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.0.generation()
     }
 
     /// Performs unchecked conversion into a typed slab map ID
@@ -71,22 +119,25 @@ impl SlabMapUntypedId {
     /// undocumented behavior if the original ID did not belong to the
     /// indexed SlabMap
     pub fn as_typed_unchecked<T>(&self) -> SlabMapId<T> {
-        SlabMapId::new(self.0)
+        SlabMapId(self.0, Default::default())
     }
 
     /// Performs unchecked conversion from a raw slab index to a SlabMap key
     ///
+    /// The resulting ID has the initial generation, so it only matches
+    /// values in slots that were never reused
+    ///
     /// Indexing directly with a resulting ID results in undesired and
     /// undocumented behavior if the original ID did not belong to the
     /// indexed SlabMap
     pub fn from_raw_unchecked(value: usize) -> SlabMapUntypedId {
-        SlabMapUntypedId::new(value)
+        SlabMapUntypedId::new(value, 0)
     }
 }
 
 impl Hash for SlabMapUntypedId {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.hash(state);
+        self.raw().hash(state);
     }
 }
 
@@ -118,145 +169,373 @@ impl<K> From<SlabMapUntypedId> for SlabMapKeyOrUntypedId<K> {
 
 #[derive(Debug, Clone)]
 pub struct SlabMap<K: Eq + Hash, V, Hasher: BuildHasher = BuildHasherDefault<ahash::AHasher>> {
-    items: Vec<V>,
-    ids: HashMap<Ref<K>, usize, Hasher>,
-    keys: Vec<Ref<K>>,
+    slots: Vec<Slot<K, V>>,
+    /// Indices of occupied slots, hashed by the keys stored in the slots
+    index: HashTable<usize>,
+    hasher: Hasher,
+    /// Indices of vacant slots, reused by the following insertions
+    free: Vec<usize>,
 }
 
-// safe because internal Rcs are not exposed by the api and the reference counts
-// only change in methods with &mut self
-unsafe impl<K: Eq + Hash + Send, V: Send, Hasher: BuildHasher + Send> Send
-    for SlabMap<K, V, Hasher>
-{
+#[derive(Debug, Clone)]
+struct Slot<K, V> {
+    generation: u32,
+    entry: Option<(K, V)>,
 }
-unsafe impl<K: Eq + Hash + Sync, V: Sync, Hasher: BuildHasher + Sync> Sync
-    for SlabMap<K, V, Hasher>
-{
+
+impl<K, V> Slot<K, V> {
+    fn key(&self) -> &K {
+        &self
+            .entry
+            .as_ref()
+            .expect("Indexed slots should be occupied")
+            .0
+    }
 }
 
 #[derive(Debug)]
 pub struct SlabMapDuplicateError<K, V>(pub K, pub V);
 
-impl<K: Eq + Hash, V, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
-    pub fn insert(&mut self, key: K, value: V) -> (SlabMapId<V>, Option<V>) {
-        match self.ids.get(Wrapper::wrap(&key)) {
-            None => {
-                let id = self.items.len();
-                self.items.push(value);
-
-                self.add_key_mapping(key, id);
+/// Table mapping ids of the values before [SlabMap::compact] or
+/// [SlabMap::sort_by_key] to their new ids
+pub struct SlabMapRemap<V> {
+    /// Generation and new slot index for every old slot index
+    ids: Vec<Option<(u32, usize)>>,
+    _data: PhantomData<V>,
+}
 
-                (SlabMapId::new(id), None)
-            }
-            Some(id) => {
-                let mut old = value;
-                std::mem::swap(&mut self.items[*id], &mut old);
-                (SlabMapId::new(*id), Some(old))
+impl<V> SlabMapRemap<V> {
+    /// New id of the value, or `None` if the old id was not pointing to a
+    /// value of the map
+    pub fn get(&self, id: SlabMapId<V>) -> Option<SlabMapId<V>> {
+        match self.ids.get(id.raw()) {
+            Some(&Some((generation, new))) if generation == id.generation() => {
+                Some(SlabMapId::new(new, 0))
             }
+            _ => None,
+        }
+    }
+
+    /// Pairs of old and new ids of all values
+    pub fn iter(&self) -> impl Iterator<Item = (SlabMapId<V>, SlabMapId<V>)> + '_ {
+        self.ids.iter().enumerate().filter_map(|(old, entry)| {
+            let (generation, new) = (*entry)?;
+            Some((SlabMapId::new(old, generation), SlabMapId::new(new, 0)))
+        })
+    }
+
+    /// Whether every value kept its id
+    pub fn is_identity(&self) -> bool {
+        self.iter().all(|(old, new)| old == new)
+    }
+}
+
+impl<V> Debug for SlabMapRemap<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V> Clone for SlabMapRemap<V> {
+    fn clone(&self) -> Self {
+        Self {
+            ids: self.ids.clone(),
+            _data: Default::default(),
         }
     }
+}
 
+impl<K: Eq + Hash, V, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
+    /// Inserts the value, replacing the value of the same key and keeping
+    /// its id
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX] + 1 slots
+    pub fn insert(&mut self, key: K, value: V) -> (SlabMapId<V>, Option<V>) {
+        self.insert_with_id(key, |_| value)
+    }
+
+    /// Same as [Self::insert], with the value built from its id
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX] + 1 slots
     pub fn insert_with_id(
         &mut self,
         key: K,
         item: impl FnOnce(SlabMapId<V>) -> V,
     ) -> (SlabMapId<V>, Option<V>) {
-        match self.ids.get(Wrapper::wrap(&key)) {
-            None => {
-                let id = self.items.len();
-                let id = SlabMapId::<V>::new(id);
-                let item = item(id);
-                self.items.push(item);
-
-                self.add_key_mapping(key, id.0);
-
+        match self.entry(key) {
+            Entry::Vacant(entry) => {
+                let id = entry.id();
+                entry.insert_with_id(item);
                 (id, None)
             }
-            Some(id) => {
-                let slab_id = SlabMapId::<V>::new(*id);
-                let mut old = item(slab_id);
-                std::mem::swap(&mut self.items[*id], &mut old);
-                (slab_id, Some(old))
+            Entry::Occupied(mut entry) => {
+                let old = entry.insert(item(entry.id()));
+                (entry.id(), Some(old))
             }
         }
     }
 
+    /// Inserts the value, failing if the key is already present
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX] + 1 slots
     pub fn insert_new(
         &mut self,
         key: K,
         value: V,
     ) -> Result<SlabMapId<V>, SlabMapDuplicateError<K, V>> {
-        if self.ids.contains_key(Wrapper::wrap(&key)) {
-            return Err(SlabMapDuplicateError(key, value));
-        }
-        let id = self.items.len();
-        self.items.push(value);
-
-        self.add_key_mapping(key, id);
-
-        Ok(SlabMapId::new(id))
+        self.insert_new_with_id(key, |_| value)
     }
 
+    /// Same as [Self::insert_new], with the value built from its id
+    ///
+    /// # Panics
+    ///
+    /// With the `compact_ids` feature, panics if the value needs a new slot
+    /// while the map already holds [MAX_INDEX] + 1 slots
     pub fn insert_new_with_id(
         &mut self,
         key: K,
         item: impl FnOnce(SlabMapId<V>) -> V,
     ) -> Result<SlabMapId<V>, SlabMapDuplicateError<K, V>> {
-        if let Some(id) = self.ids.get(Wrapper::wrap(&key)) {
-            return Err(SlabMapDuplicateError(key, item(SlabMapId::new(*id))));
+        match self.entry(key) {
+            Entry::Vacant(entry) => {
+                let id = entry.id();
+                entry.insert_with_id(item);
+                Ok(id)
+            }
+            Entry::Occupied(entry) => {
+                let value = item(entry.id());
+                Err(SlabMapDuplicateError(entry.into_key(), value))
+            }
+        }
+    }
+
+    /// Entry of the key for in-place lookup and insertion, hashing the key
+    /// only once
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, Hasher> {
+        let hash = self.hasher.hash_one(&key);
+        match self.find_hashed(hash, &key) {
+            Some(index) => Entry::Occupied(OccupiedEntry {
+                id: SlabMapId::new(index, self.slots[index].generation),
+                map: self,
+                key,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                hash,
+                key,
+            }),
         }
+    }
+
+    /// Id the next inserted value is going to get
+    fn next_id(&self) -> SlabMapId<V> {
+        match self.free.last() {
+            Some(&index) => SlabMapId::new(index, self.slots[index].generation),
+            None => SlabMapId::new(self.slots.len(), 0),
+        }
+    }
+
+    /// Index of the slot holding the key
+    fn find_index<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.find_hashed(self.hasher.hash_one(key), key)
+    }
+
+    /// Same as [Self::find_index], with the hash of the key already computed
+    fn find_hashed<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.index
+            .find(hash, |&index| self.slots[index].key().borrow() == key)
+            .copied()
+    }
+
+    /// Stores the value in the slot of the id returned by [Self::next_id]
+    fn fill_slot(&mut self, id: SlabMapId<V>, key: K, value: V) {
+        let hash = self.hasher.hash_one(&key);
+        self.fill_slot_hashed(id, hash, key, value)
+    }
+
+    /// Same as [Self::fill_slot], with the hash of the key already computed
+    fn fill_slot_hashed(&mut self, id: SlabMapId<V>, hash: u64, key: K, value: V) {
+        let (slots, hasher) = (&self.slots, &self.hasher);
+        self.index
+            .insert_unique(hash, id.raw(), |&index| hasher.hash_one(slots[index].key()));
+        let entry = Some((key, value));
+        if id.raw() == self.slots.len() {
+            self.slots.push(Slot {
+                generation: id.generation(),
+                entry,
+            });
+        } else {
+            let reused = self.free.pop();
+            debug_assert_eq!(reused, Some(id.raw()));
+            self.slots[id.raw()].entry = entry;
+        }
+    }
+
+    /// Removes the value with the given id, returning its key and the value
+    ///
+    /// Slot of the value gets reused by one of the following insertions, and
+    /// the removed id is no longer valid for this map
+    pub fn remove_by_id(&mut self, id: SlabMapId<V>) -> Option<(K, V)> {
+        if !self.contains_id(id) {
+            return None;
+        }
+        let slot = &mut self.slots[id.raw()];
+        let (key, value) = slot.entry.take().expect("Slot was checked to be occupied");
+        // Slots that ran out of generations are retired, so old ids can never
+        // match a new value
+        if slot.generation < MAX_GENERATION {
+            slot.generation += 1;
+            self.free.push(id.raw());
+        }
+
+        let hash = self.hasher.hash_one(&key);
+        self.index
+            .find_entry(hash, |&index| index == id.raw())
+            .expect("Occupied slots should be indexed")
+            .remove();
+        Some((key, value))
+    }
+
+    /// Removes the value with the given key, returning its id and the value
+    ///
+    /// See [Self::remove_by_id]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(SlabMapId<V>, V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let id = self.key_to_id(key)?;
+        self.remove_by_id(id).map(|(_, value)| (id, value))
+    }
 
-        let id = self.items.len();
-        let id = SlabMapId::<V>::new(id);
+    /// Moves all values into densely packed slots, keeping their order
+    ///
+    /// Vacant slots left by removals are dropped and generations of all
+    /// slots are reset, so every id of the map changes meaning. The returned
+    /// table maps the old ids to the new ones
+    pub fn compact(&mut self) -> SlabMapRemap<V> {
+        let (old_len, entries) = self.take_entries();
+        self.rebuild(old_len, entries)
+    }
 
-        let item = item(id);
-        self.items.push(item);
+    /// Same as [Self::compact], but orders values by the result of `f`
+    ///
+    /// Sorting is stable, use `|key, _| key.clone()` to order values by
+    /// their keys
+    pub fn sort_by_key<O: Ord>(&mut self, mut f: impl FnMut(&K, &V) -> O) -> SlabMapRemap<V> {
+        let (old_len, mut entries) = self.take_entries();
+        entries.sort_by_key(|(_, key, value)| f(key, value));
+        self.rebuild(old_len, entries)
+    }
+
+    /// Empties the map, returning the number of slots and all values with
+    /// their ids
+    fn take_entries(&mut self) -> (usize, Vec<(SlabMapId<V>, K, V)>) {
+        let slots = std::mem::take(&mut self.slots);
+        self.index.clear();
+        self.free.clear();
+        let old_len = slots.len();
+        let entries = slots
+            .into_iter()
+            .enumerate()
+            .filter_map(|(id, slot)| {
+                let (key, value) = slot.entry?;
+                Some((SlabMapId::new(id, slot.generation), key, value))
+            })
+            .collect();
+        (old_len, entries)
+    }
 
-        self.add_key_mapping(key, id.0);
+    /// Inserts values into the empty map in the order of `entries`
+    fn rebuild(&mut self, old_len: usize, entries: Vec<(SlabMapId<V>, K, V)>) -> SlabMapRemap<V> {
+        let mut ids = vec![None; old_len];
+        for (old, key, value) in entries {
+            let id = self.next_id();
+            ids[old.raw()] = Some((old.generation(), id.raw()));
+            self.fill_slot(id, key, value);
+        }
+        SlabMapRemap {
+            ids,
+            _data: Default::default(),
+        }
+    }
 
-        Ok(id)
+    /// Number of values in the map
+    pub fn len(&self) -> usize {
+        self.index.len()
     }
 
-    fn add_key_mapping(&mut self, key: K, id: usize) {
-        let key = Ref(Rc::new(key));
-        self.keys.push(key.clone());
-        self.ids.insert(key, id);
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
 
-        debug_assert_eq!(self.items.len(), self.keys.len());
-        debug_assert_eq!(self.items.len(), self.ids.len());
+    /// Occupied slot of the raw index
+    fn entry_by_raw(&self, id: usize) -> Option<(SlabMapId<V>, &K, &V)> {
+        let slot = self.slots.get(id)?;
+        let (key, value) = slot.entry.as_ref()?;
+        Some((SlabMapId::new(id, slot.generation), key, value))
     }
 
     pub fn get_by_id(&self, id: SlabMapId<V>) -> Option<&V> {
-        self.items.get(id.0)
+        self.get_by_untyped_id(id.as_untyped())
     }
 
     pub fn get_by_id_mut(&mut self, id: SlabMapId<V>) -> Option<&mut V> {
-        self.items.get_mut(id.0)
+        self.get_by_untyped_id_mut(id.as_untyped())
     }
     pub fn contains_id(&self, id: SlabMapId<V>) -> bool {
-        id.0 < self.items.len()
+        self.contains_untyped_id(id.as_untyped())
     }
 
     pub fn get_by_untyped_id(&self, id: SlabMapUntypedId) -> Option<&V> {
-        self.items.get(id.0)
+        let slot = self
+            .slots
+            .get(id.raw())
+            .filter(|slot| slot.generation == id.generation())?;
+        slot.entry.as_ref().map(|(_, value)| value)
     }
 
     pub fn get_by_untyped_id_mut(&mut self, id: SlabMapUntypedId) -> Option<&mut V> {
-        self.items.get_mut(id.0)
+        let slot = self
+            .slots
+            .get_mut(id.raw())
+            .filter(|slot| slot.generation == id.generation())?;
+        slot.entry.as_mut().map(|(_, value)| value)
     }
     pub fn contains_untyped_id(&self, id: SlabMapUntypedId) -> bool {
-        id.0 < self.items.len()
+        self.get_by_untyped_id(id).is_some()
     }
 
+    /// Value stored in the slot with the given index, regardless of the slot
+    /// generation
     pub fn get_by_raw(&self, id: usize) -> Option<&V> {
-        self.items.get(id)
+        self.entry_by_raw(id).map(|(_, _, value)| value)
     }
 
     pub fn get_by_raw_mut(&mut self, id: usize) -> Option<&mut V> {
-        self.items.get_mut(id)
+        let slot = self.slots.get_mut(id)?;
+        slot.entry.as_mut().map(|(_, value)| value)
     }
     pub fn contains_raw(&self, id: usize) -> bool {
-        id < self.items.len()
+        self.entry_by_raw(id).is_some()
     }
 
     pub fn get_by_key<Q>(&self, key: &Q) -> Option<&V>
@@ -264,9 +543,7 @@ impl<K: Eq + Hash, V, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
         K: Borrow<Q>,
         Q: Eq + Hash + ?Sized,
     {
-        self.ids
-            .get(Wrapper::wrap(key))
-            .and_then(|e| self.get_by_raw(*e))
+        self.find_index(key).and_then(|e| self.get_by_raw(e))
     }
 
     pub fn get_by_key_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
@@ -274,17 +551,14 @@ impl<K: Eq + Hash, V, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
         K: Borrow<Q>,
         Q: Eq + Hash + ?Sized,
     {
-        self.ids
-            .get(Wrapper::wrap(key))
-            .copied()
-            .and_then(|e| self.get_by_raw_mut(e))
+        self.find_index(key).and_then(|e| self.get_by_raw_mut(e))
     }
     pub fn contains_key<Q>(&self, key: &Q) -> bool
     where
         K: Borrow<Q>,
         Q: Eq + Hash + ?Sized,
     {
-        self.ids.contains_key(Wrapper::wrap(key))
+        self.find_index(key).is_some()
     }
 
     pub fn get(&self, k: SlabMapKeyOrId<K, V>) -> Option<&V> {
@@ -303,14 +577,14 @@ impl<K: Eq + Hash, V, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
 
     pub fn get_by_untyped(&self, k: SlabMapKeyOrUntypedId<K>) -> Option<&V> {
         match k {
-            SlabMapKeyOrUntypedId::Id(id) => self.get_by_raw(id.0),
+            SlabMapKeyOrUntypedId::Id(id) => self.get_by_untyped_id(id),
             SlabMapKeyOrUntypedId::Key(key) => self.get_by_key(&key),
         }
     }
 
     pub fn get_by_untyped_mut(&mut self, k: SlabMapKeyOrUntypedId<K>) -> Option<&mut V> {
         match k {
-            SlabMapKeyOrUntypedId::Id(id) => self.get_by_raw_mut(id.0),
+            SlabMapKeyOrUntypedId::Id(id) => self.get_by_untyped_id_mut(id),
             SlabMapKeyOrUntypedId::Key(key) => self.get_by_key_mut(&key),
         }
     }
@@ -320,66 +594,67 @@ impl<K: Eq + Hash, V, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
         K: Borrow<Q>,
         Q: Eq + Hash + ?Sized,
     {
-        self.ids.get(Wrapper::wrap(key)).map(|e| SlabMapId::new(*e))
+        self.find_index(key)
+            .map(|e| SlabMapId::new(e, self.slots[e].generation))
     }
 
     pub fn id_to_key(&self, id: SlabMapId<V>) -> Option<&K> {
-        self.keys.get(id.0).map(|r| &*r.0)
+        self.untyped_to_key(id.as_untyped())
     }
 
     pub fn untyped_to_key(&self, id: SlabMapUntypedId) -> Option<&K> {
-        self.keys.get(id.0).map(|r| &*r.0)
+        let slot = self
+            .slots
+            .get(id.raw())
+            .filter(|slot| slot.generation == id.generation())?;
+        slot.entry.as_ref().map(|(key, _)| key)
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (SlabMapId<V>, &K, &V)> {
+        self.slots.iter().enumerate().filter_map(|(id, slot)| {
+            let (key, value) = slot.entry.as_ref()?;
+            Some((SlabMapId::new(id, slot.generation), key, value))
+        })
     }
 
     pub fn values(&self) -> impl Iterator<Item = &V> {
-        self.items.iter()
+        self.entries().map(|(_, _, value)| value)
     }
 
     pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
-        self.items.iter_mut()
+        self.iter_mut().map(|(_, value)| value)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (SlabMapId<V>, &V)> {
-        self.items
-            .iter()
-            .enumerate()
-            .map(|(id, e)| (SlabMapId::new(id), e))
+        self.entries().map(|(id, _, value)| (id, value))
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (SlabMapId<V>, &mut V)> {
-        self.items
-            .iter_mut()
-            .enumerate()
-            .map(|(id, e)| (SlabMapId::new(id), e))
+        self.slots.iter_mut().enumerate().filter_map(|(id, slot)| {
+            let (_, value) = slot.entry.as_mut()?;
+            Some((SlabMapId::new(id, slot.generation), value))
+        })
     }
 
+    /// Consumes the map, returning keys, raw slot indices, and values
+    #[allow(clippy::should_implement_trait)]
     pub fn into_iter(self) -> impl Iterator<Item = (K, usize, V)> {
-        // drop keys map to free RCs
-        drop(self.ids);
-
-        self.keys
-            .into_iter()
-            .zip(self.items)
-            .enumerate()
-            .map(|(id, (key, value))| {
-                let key = Rc::into_inner(key.0).expect("Key RCs should be freed");
-                (key, id, value)
-            })
+        self.slots.into_iter().enumerate().filter_map(|(id, slot)| {
+            let (key, value) = slot.entry?;
+            Some((key, id, value))
+        })
     }
 
     pub fn keys(&self) -> impl Iterator<Item = &K> {
-        self.keys.iter().map(|k| &*k.0)
+        self.entries().map(|(_, key, _)| key)
     }
 
     pub fn ids(&self) -> impl Iterator<Item = SlabMapId<V>> + '_ {
-        self.ids.iter().enumerate().map(|(i, _)| SlabMapId::new(i))
+        self.entries().map(|(id, _, _)| id)
     }
 
     pub fn keys_ids(&self) -> impl Iterator<Item = (&'_ K, SlabMapId<V>)> + '_ {
-        self.keys
-            .iter()
-            .enumerate()
-            .map(|(id, k)| (&*k.0, SlabMapId::new(id)))
+        self.entries().map(|(id, key, _)| (key, id))
     }
 }
 
@@ -387,32 +662,25 @@ impl<K: Eq + Hash, V, Hasher: BuildHasher> Index<SlabMapId<V>> for SlabMap<K, V,
     type Output = V;
 
     fn index(&self, index: SlabMapId<V>) -> &Self::Output {
-        &self.items[index.0]
+        self.get_by_id(index)
+            .expect("SlabMap id should point to an existing value")
     }
 }
 
 impl<K: Eq + Hash, V, Hasher: BuildHasher> IndexMut<SlabMapId<V>> for SlabMap<K, V, Hasher> {
     fn index_mut(&mut self, index: SlabMapId<V>) -> &mut Self::Output {
-        &mut self.items[index.0]
+        self.get_by_id_mut(index)
+            .expect("SlabMap id should point to an existing value")
     }
 }
 
 impl<K: Eq + Hash, V, Hasher: BuildHasher + Default> Default for SlabMap<K, V, Hasher> {
     fn default() -> Self {
         Self {
-            items: Default::default(),
-            ids: Default::default(),
-            keys: Default::default(),
+            slots: Default::default(),
+            index: Default::default(),
+            hasher: Default::default(),
+            free: Default::default(),
         }
     }
 }
-
-#[cfg(feature = "serde")]
-impl<'de, K: serde::Deserialize<'de>, V> serde::Deserialize<'de> for SlabMapKeyOrId<K, V> {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        K::deserialize(deserializer).map(|k| Self::Key(k))
-    }
-}
@@ -0,0 +1,39 @@
+//! Parallel iterators over [SlabMap] values, backed by `rayon`
+use std::hash::{BuildHasher, Hash};
+
+use rayon::prelude::*;
+
+use crate::{SlabMap, SlabMapId};
+
+impl<K: Eq + Hash + Sync, V: Sync, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
+    /// Parallel version of [SlabMap::iter]
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (SlabMapId<V>, &V)> {
+        self.slots
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let (_, value) = slot.entry.as_ref()?;
+                Some((SlabMapId::new(index, slot.generation), value))
+            })
+    }
+
+    /// Parallel version of [SlabMap::values]
+    pub fn par_values(&self) -> impl ParallelIterator<Item = &V> {
+        self.slots
+            .par_iter()
+            .filter_map(|slot| slot.entry.as_ref().map(|(_, value)| value))
+    }
+}
+
+impl<K: Eq + Hash + Send, V: Send, Hasher: BuildHasher> SlabMap<K, V, Hasher> {
+    /// Parallel version of [SlabMap::iter_mut]
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (SlabMapId<V>, &mut V)> {
+        self.slots
+            .par_iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let (_, value) = slot.entry.as_mut()?;
+                Some((SlabMapId::new(index, slot.generation), value))
+            })
+    }
+}
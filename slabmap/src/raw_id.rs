@@ -0,0 +1,87 @@
+//! Storage of slot indices and generations inside of ids
+//!
+//! By default ids hold a full `usize` index and `u32` generation. With the
+//! `compact_ids` feature both are packed into a single [NonZeroU32], making
+//! ids 4 bytes large, including `Option`s of ids. Compact ids limit maps to
+//! [MAX_INDEX] slots, and slots are retired after [MAX_GENERATION] removals
+#[cfg(feature = "compact_ids")]
+use std::num::NonZeroU32;
+
+/// Slot index and generation of an id
+#[cfg(not(feature = "compact_ids"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct RawId(usize, u32);
+
+/// Highest slot index an id can hold
+#[cfg(not(feature = "compact_ids"))]
+pub const MAX_INDEX: usize = usize::MAX;
+
+/// Highest generation an id can hold
+#[cfg(not(feature = "compact_ids"))]
+pub const MAX_GENERATION: u32 = u32::MAX;
+
+#[cfg(not(feature = "compact_ids"))]
+impl RawId {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        Self(index, generation)
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self.0
+    }
+
+    pub(crate) fn generation(self) -> u32 {
+        self.1
+    }
+
+    /// Same as [RawId::new], returning `None` if the index or the generation
+    /// can't be stored
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn checked(index: usize, generation: u32) -> Option<Self> {
+        Some(Self::new(index, generation))
+    }
+}
+
+/// Slot index and generation of an id, packed as `generation << 24 |
+/// (index + 1)`
+#[cfg(feature = "compact_ids")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct RawId(NonZeroU32);
+
+#[cfg(feature = "compact_ids")]
+const INDEX_BITS: u32 = 24;
+
+/// Highest slot index an id can hold
+#[cfg(feature = "compact_ids")]
+pub const MAX_INDEX: usize = (1 << INDEX_BITS) - 2;
+
+/// Highest generation an id can hold
+#[cfg(feature = "compact_ids")]
+pub const MAX_GENERATION: u32 = u32::MAX >> INDEX_BITS;
+
+#[cfg(feature = "compact_ids")]
+impl RawId {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        assert!(
+            index <= MAX_INDEX,
+            "SlabMap with compact ids can't hold more than {} slots",
+            MAX_INDEX + 1
+        );
+        debug_assert!(generation <= MAX_GENERATION);
+        let packed = (generation << INDEX_BITS) | (index as u32 + 1);
+        Self(NonZeroU32::new(packed).expect("Packed index is never zero"))
+    }
+
+    pub(crate) fn index(self) -> usize {
+        (self.0.get() & ((1 << INDEX_BITS) - 1)) as usize - 1
+    }
+
+    pub(crate) fn generation(self) -> u32 {
+        self.0.get() >> INDEX_BITS
+    }
+
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn checked(index: usize, generation: u32) -> Option<Self> {
+        (index <= MAX_INDEX && generation <= MAX_GENERATION).then(|| Self::new(index, generation))
+    }
+}
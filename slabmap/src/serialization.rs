@@ -0,0 +1,260 @@
+//! `serde` support for [SlabMap] and [SlabMapId]
+//!
+//! Maps are serialized as sequences of their slots, so deserialized maps
+//! keep ids of all values, and ids of removed values stay stale. Vacant
+//! slots store their position in the free list, so following insertions
+//! reuse slots in the same order as before serialization
+//!
+//! Ids are serialized as `(index, generation)` pairs by default. Inside
+//! [with_id_keys], ids of the values of the provided map are serialized as
+//! keys of those values instead, and are deserialized by looking the keys up
+//! in the map, which keeps persisted data readable and independent of the
+//! insertion order
+use std::any::{type_name, TypeId};
+use std::cell::RefCell;
+use std::fmt::Formatter;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use ahash::AHashMap;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::raw_id::RawId;
+use crate::{SlabMap, SlabMapId, SlabMapKeyOrId, Slot, MAX_GENERATION};
+
+#[derive(Serialize)]
+struct SlotRef<'a, K, V> {
+    #[serde(skip_serializing_if = "is_zero")]
+    generation: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<&'a K>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<&'a V>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "K: Deserialize<'de>, V: Deserialize<'de>"))]
+struct SlotOwned<K, V> {
+    #[serde(default)]
+    generation: u32,
+    key: Option<K>,
+    value: Option<V>,
+    #[serde(default)]
+    free: Option<usize>,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+impl<K: Eq + Hash + Serialize, V: Serialize, Hasher: BuildHasher> Serialize
+    for SlabMap<K, V, Hasher>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut free = vec![None; self.slots.len()];
+        for (position, index) in self.free.iter().enumerate() {
+            free[*index] = Some(position);
+        }
+        let mut seq = serializer.serialize_seq(Some(self.slots.len()))?;
+        for (slot, free) in self.slots.iter().zip(free) {
+            let (key, value) = slot.entry.as_ref().map(|(k, v)| (k, v)).unzip();
+            seq.serialize_element(&SlotRef {
+                generation: slot.generation,
+                key,
+                value,
+                free,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, K, V, Hasher> Deserialize<'de> for SlabMap<K, V, Hasher>
+where
+    K: Eq + Hash + Deserialize<'de>,
+    V: Deserialize<'de>,
+    Hasher: BuildHasher + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MapVisitor<K, V, Hasher>(PhantomData<(K, V, Hasher)>);
+
+        impl<'de, K, V, Hasher> Visitor<'de> for MapVisitor<K, V, Hasher>
+        where
+            K: Eq + Hash + Deserialize<'de>,
+            V: Deserialize<'de>,
+            Hasher: BuildHasher + Default,
+        {
+            type Value = SlabMap<K, V, Hasher>;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("a sequence of slab map slots")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut map = SlabMap::<K, V, Hasher>::default();
+                let mut vacant = vec![];
+                while let Some(slot) = seq.next_element::<SlotOwned<K, V>>()? {
+                    let index = map.slots.len();
+                    if RawId::checked(index, slot.generation).is_none() {
+                        return Err(A::Error::custom(format!(
+                            "slot {index} is out of range of ids"
+                        )));
+                    }
+                    match (slot.key, slot.value) {
+                        (Some(key), Some(value)) => {
+                            if map.contains_key(&key) {
+                                return Err(A::Error::custom(format!(
+                                    "duplicate key in slot {index}"
+                                )));
+                            }
+                            let id = SlabMapId::new(index, slot.generation);
+                            map.fill_slot(id, key, value);
+                        }
+                        (None, None) => {
+                            map.slots.push(Slot {
+                                generation: slot.generation,
+                                entry: None,
+                            });
+                            vacant.push((slot.free, index));
+                        }
+                        _ => {
+                            return Err(A::Error::custom(format!(
+                                "slot {index} must have either both key and value, or neither"
+                            )))
+                        }
+                    }
+                }
+                // Slots without the position, written by older versions, go
+                // to the bottom of the free list in their index order
+                vacant.sort_by_key(|(position, _)| *position);
+                // Retired slots never get reused
+                map.free = vacant
+                    .into_iter()
+                    .map(|(_, index)| index)
+                    .filter(|index| map.slots[*index].generation != MAX_GENERATION)
+                    .collect();
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_seq(MapVisitor(PhantomData))
+    }
+}
+
+/// Keys of a single map, used for (de)serializing its ids
+struct IdKeys {
+    keys: Vec<(u32, Option<String>)>,
+    ids: AHashMap<String, (usize, u32)>,
+}
+
+thread_local! {
+    static ID_KEYS: RefCell<AHashMap<TypeId, IdKeys>> = RefCell::default();
+}
+
+/// Runs the closure with ids of the values of `map` being serialized as
+/// their keys on the current thread
+///
+/// Calls can be nested to use the keys of several maps at once, with inner
+/// calls taking precedence for maps of the same value type
+pub fn with_id_keys<K, V, Hasher, R>(map: &SlabMap<K, V, Hasher>, func: impl FnOnce() -> R) -> R
+where
+    K: Eq + Hash + AsRef<str>,
+    V: 'static,
+    Hasher: BuildHasher,
+{
+    struct Reset(TypeId, Option<IdKeys>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            let previous = self.1.take();
+            ID_KEYS.with(|keys| match previous {
+                None => keys.borrow_mut().remove(&self.0),
+                Some(previous) => keys.borrow_mut().insert(self.0, previous),
+            });
+        }
+    }
+
+    let keys = IdKeys {
+        keys: map
+            .slots
+            .iter()
+            .map(|slot| {
+                let key = slot.entry.as_ref().map(|(k, _)| k.as_ref());
+                (slot.generation, key.map(str::to_string))
+            })
+            .collect(),
+        ids: map
+            .keys_ids()
+            .map(|(key, id)| (key.as_ref().to_string(), (id.raw(), id.generation())))
+            .collect(),
+    };
+    let type_id = TypeId::of::<V>();
+    let previous = ID_KEYS.with(|k| k.borrow_mut().insert(type_id, keys));
+    let _reset = Reset(type_id, previous);
+    func()
+}
+
+impl<V: 'static> Serialize for SlabMapId<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let key = ID_KEYS.with(|keys| {
+            let keys = keys.borrow();
+            let map = keys.get(&TypeId::of::<V>())?;
+            let key = match map.keys.get(self.raw()) {
+                Some((generation, Some(key))) if *generation == self.generation() => {
+                    Some(key.clone())
+                }
+                _ => None,
+            };
+            Some(key)
+        });
+        match key {
+            None => (self.raw(), self.generation()).serialize(serializer),
+            Some(Some(key)) => serializer.serialize_str(&key),
+            Some(None) => Err(serde::ser::Error::custom(format!(
+                "{self:?} is not present in the map of {}",
+                type_name::<V>()
+            ))),
+        }
+    }
+}
+
+impl<'de, V: 'static> Deserialize<'de> for SlabMapId<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let keyed = ID_KEYS.with(|keys| keys.borrow().contains_key(&TypeId::of::<V>()));
+        if !keyed {
+            let (index, generation) = <(usize, u32)>::deserialize(deserializer)?;
+            return RawId::checked(index, generation)
+                .map(|id| SlabMapId(id, PhantomData))
+                .ok_or_else(|| {
+                    D::Error::custom(format!("id ({index}, {generation}) is out of range"))
+                });
+        }
+
+        let key = String::deserialize(deserializer)?;
+        let id = ID_KEYS.with(|keys| {
+            keys.borrow()
+                .get(&TypeId::of::<V>())
+                .and_then(|map| map.ids.get(&key).copied())
+        });
+        match id {
+            Some((index, generation)) => Ok(SlabMapId::new(index, generation)),
+            None => Err(D::Error::custom(format!(
+                "key `{key}` is not present in the map of {}",
+                type_name::<V>()
+            ))),
+        }
+    }
+}
+
+impl<'de, K: Deserialize<'de>, V> Deserialize<'de> for SlabMapKeyOrId<K, V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        K::deserialize(deserializer).map(|k| Self::Key(k))
+    }
+}